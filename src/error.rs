@@ -0,0 +1,386 @@
+//! Nameable error types.
+
+use alloc::boxed::Box;
+use core::error::Error as StdError;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use super::{BoxError, ErrorRef};
+
+/// A boxed error that can be named in function signatures.
+///
+/// The functions of this crate return `impl Error`, which can't be written
+/// in the signature of a public function. An `errors::Error` can, and any
+/// value that converts into a `Box<dyn Error + Send + Sync>` can be turned
+/// into one with `?`.
+///
+/// It follows the same formatting rules as the errors created with
+/// [`errors::new`](::new), adapting any other error like
+/// [`errors::fmt`](::fmt()).
+///
+/// # Example
+///
+/// ```
+/// fn launch() -> Result<(), errors::Error> {
+///     Err(errors::wrap("ship exploded", "cat hair in generator"))?;
+///     Ok(())
+/// }
+///
+/// let err = launch().unwrap_err();
+/// assert_eq!(format!("{:+}", err), "ship exploded: cat hair in generator");
+/// ```
+///
+/// # Using as a source
+///
+/// To be convertible from *any* error, `errors::Error` can't itself
+/// implement `std::error::Error`, since that would conflict with the
+/// standard `impl<T> From<T> for T`. Instead, it dereferences to a
+/// `dyn Error + Send + Sync`, and [`into_inner`](Error::into_inner) returns
+/// the boxed error, so it can still be the cause of
+/// [`errors::wrap`](::wrap):
+///
+/// ```
+/// use std::error::Error;
+///
+/// let err = errors::Error::from("cat hair in generator");
+/// let err = errors::wrap("ship exploded", err.into_inner());
+///
+/// assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
+/// ```
+pub struct Error(BoxError);
+
+impl Error {
+    /// Get a reference to the inner error, with a `'static` lifetime.
+    ///
+    /// This is needed by the functions in [`errors::iter`](::iter).
+    pub fn as_error(&self) -> &ErrorRef {
+        &*self.0
+    }
+
+    /// Consume this `Error`, returning the inner boxed error.
+    pub fn into_inner(self) -> BoxError {
+        self.0
+    }
+}
+
+impl<E> From<E> for Error
+where
+    E: Into<BoxError>,
+{
+    fn from(err: E) -> Error {
+        Error(err.into())
+    }
+}
+
+impl From<Error> for Box<dyn StdError> {
+    fn from(err: Error) -> Box<dyn StdError> {
+        err.0
+    }
+}
+
+impl Deref for Error {
+    type Target = dyn StdError + Send + Sync;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl DerefMut for Error {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut *self.0
+    }
+}
+
+impl AsRef<dyn StdError + Send + Sync> for Error {
+    fn as_ref(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        &*self.0
+    }
+}
+
+impl AsRef<ErrorRef> for Error {
+    fn as_ref(&self) -> &ErrorRef {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        debug(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display(&*self.0, f)
+    }
+}
+
+/// An error that displays its whole message chain, for embedding in the
+/// message of another error.
+///
+/// Errors that only print their own message in `{}` lose their source chain
+/// when another error includes them in its message, such as with
+/// `#[error("config: {0}")]` of `thiserror`. A `Source` prints the chain
+/// like `{:+}` in any form, so it ends the chain: its `source()` is `None`.
+/// The error including it can still return it from its own `source()`,
+/// which the formatting of this crate stops at, since the chain is already
+/// in that error's message. The inner error itself is still found by type,
+/// such as with [`errors::is`](::is), though its sources aren't.
+///
+/// # Example
+///
+/// ```
+/// use std::fmt;
+///
+/// #[derive(Debug)]
+/// enum AppError {
+///     Config(errors::Source),
+/// }
+///
+/// impl fmt::Display for AppError {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         match self {
+///             AppError::Config(src) => write!(f, "config: {}", src),
+///         }
+///     }
+/// }
+///
+/// impl std::error::Error for AppError {
+///     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+///         match self {
+///             AppError::Config(src) => Some(src),
+///         }
+///     }
+/// }
+///
+/// let err = AppError::Config(errors::Source::new(errors::wrap("not found", "ship.toml")));
+///
+/// assert_eq!(err.to_string(), "config: not found: ship.toml");
+/// assert_eq!(format!("{:+}", errors::fmt(&err)), "config: not found: ship.toml");
+/// ```
+pub struct Source(BoxError);
+
+impl Source {
+    /// Create a `Source` from any error.
+    pub fn new<E: Into<BoxError>>(err: E) -> Source {
+        Source(err.into())
+    }
+
+    /// Consume this `Source`, returning the inner boxed error.
+    pub fn into_inner(self) -> BoxError {
+        self.0
+    }
+
+    pub(crate) fn as_error(&self) -> &ErrorRef {
+        &*self.0
+    }
+}
+
+impl From<BoxError> for Source {
+    fn from(err: BoxError) -> Source {
+        Source(err)
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        debug(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Nothing follows a `Source` in the chain, so even {:-} prints the
+        // rest of it here.
+        if f.alternate() {
+            write!(f, "{:+#}", Display(&*self.0))
+        } else {
+            write!(f, "{:+}", Display(&*self.0))
+        }
+    }
+}
+
+impl StdError for Source {}
+
+/// An error with a static message, that can be built in a `const` context.
+///
+/// The functions of this crate return `impl Error`, which can't be stored in
+/// a `static`. A `StaticError` can, and needs no allocation. Since it is
+/// `Copy`, it can be used as the cause of [`errors::wrap`](::wrap) straight
+/// from the `static`.
+///
+/// # Example
+///
+/// ```
+/// static NOT_FOUND: errors::StaticError = errors::StaticError::new("not found");
+///
+/// let err = errors::wrap("failed to load ship.toml", NOT_FOUND);
+///
+/// assert_eq!(format!("{:+}", err), "failed to load ship.toml: not found");
+/// assert_eq!(errors::find::<errors::StaticError>(&err), Some(&NOT_FOUND));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticError(&'static str);
+
+impl StaticError {
+    /// Create a `StaticError` with a message.
+    pub const fn new(message: &'static str) -> StaticError {
+        StaticError(message)
+    }
+
+    /// Get the message of this error.
+    pub const fn message(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl fmt::Display for StaticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl StdError for StaticError {}
+
+fn debug(err: &ErrorRef, f: &mut fmt::Formatter) -> fmt::Result {
+    if ::new::follows_format(err) {
+        fmt::Debug::fmt(err, f)
+    } else {
+        fmt::Debug::fmt(&::fmt(err), f)
+    }
+}
+
+fn display(err: &ErrorRef, f: &mut fmt::Formatter) -> fmt::Result {
+    if ::new::follows_format(err) {
+        fmt::Display::fmt(err, f)
+    } else {
+        fmt::Display::fmt(&::fmt(err), f)
+    }
+}
+
+struct Display<'a>(&'a ErrorRef);
+
+impl<'a> fmt::Display for Display<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display(self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::ToString;
+    use core::error::Error as StdError;
+    use core::fmt;
+    use std::io;
+    use super::{Error, Source, StaticError};
+
+    #[test]
+    fn formats_like_wrapper() {
+        let wrapped = ::wrap("ship exploded", ::new("cat hair in generator"));
+        let expected = (
+            format!("{}", wrapped),
+            format!("{:+}", wrapped),
+            format!("{:+.0}", wrapped),
+        );
+
+        let err = Error::from(wrapped);
+        assert_eq!(
+            (format!("{}", err), format!("{:+}", err), format!("{:+.0}", err)),
+            expected,
+        );
+    }
+
+    #[test]
+    fn formats_foreign_chain() {
+        let err = Error::from(io::Error::other(::wrap("ship exploded", "cat hair")));
+        let (plain, alternate) = (format!("{:+}", err), format!("{:+#}", err));
+        let foreign = err.into_inner();
+        assert_eq!(plain, format!("{:+}", ::fmt(&*foreign)));
+        assert_eq!(alternate, format!("{:+#}", ::fmt(&*foreign)));
+    }
+
+    #[test]
+    fn from_and_source_round_trip() {
+        let err: Error = ::wrap_boxed("ship exploded", "cat hair in generator").into();
+        assert_eq!(err.to_string(), "ship exploded");
+        assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
+
+        let boxed: Box<dyn StdError + Send + Sync> = err.into_inner();
+        assert_eq!(format!("{:+}", ::fmt(&*boxed)), "ship exploded: cat hair in generator");
+        assert!(::is::<fmt::Error>(&*Error::from(fmt::Error).into_inner()));
+    }
+
+    #[derive(Debug)]
+    enum AppError {
+        Config(Source),
+    }
+
+    impl fmt::Display for AppError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                AppError::Config(src) => write!(f, "config failed: {}", src),
+            }
+        }
+    }
+
+    impl StdError for AppError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            match self {
+                AppError::Config(src) => Some(src),
+            }
+        }
+    }
+
+    #[test]
+    fn source_displays_chain() {
+        let src = Source::new(::wrap("ship.toml", io::Error::other("not found")));
+        assert_eq!(src.to_string(), "ship.toml: not found");
+        assert!(src.source().is_none());
+        assert_eq!(format!("{:+}", src), "ship.toml: not found");
+        assert_eq!(format!("{:-}", src), "ship.toml: not found");
+
+        let err = AppError::Config(src);
+        assert_eq!(err.to_string(), "config failed: ship.toml: not found");
+        assert_eq!(format!("{:+}", ::fmt(&err)), "config failed: ship.toml: not found");
+        assert_eq!(
+            format!("{:+}", ::wrap("launch", err)),
+            "launch: config failed: ship.toml: not found"
+        );
+    }
+
+    #[test]
+    fn source_is_inner() {
+        let err = AppError::Config(Source::new(io::Error::other("disk")));
+        assert!(::is::<io::Error>(&err));
+        assert_eq!(::find::<io::Error>(&err).unwrap().to_string(), "disk");
+        assert_eq!(format!("{:+}", ::fmt(&err)), "config failed: disk");
+    }
+
+    const NOT_FOUND: StaticError = StaticError::new("not found");
+    static DENIED: StaticError = StaticError::new("permission denied");
+
+    #[test]
+    fn static_error() {
+        assert_eq!(NOT_FOUND.to_string(), "not found");
+        assert_eq!(DENIED.message(), "permission denied");
+        assert!(DENIED.source().is_none());
+
+        let err = ::wrap("failed to load ship.toml", DENIED);
+        assert_eq!(format!("{:+}", err), "failed to load ship.toml: permission denied");
+        assert_eq!(::find::<StaticError>(&err), Some(&DENIED));
+        assert_eq!(format!("{:+#}", ::fmt(&NOT_FOUND)), "not found");
+    }
+
+    #[test]
+    fn question_mark_converts() {
+        fn parse() -> Result<u8, Error> {
+            Ok("256".parse::<u8>()?)
+        }
+
+        let err = parse().unwrap_err();
+        assert_eq!(err.to_string(), "number too large to fit in target type");
+        assert!(::is::<::core::num::ParseIntError>(err.as_error()));
+    }
+}
@@ -0,0 +1,205 @@
+//! Extension traits for adding context to errors.
+
+use core::fmt;
+use core::error::Error;
+use super::BoxError;
+use fmt::Report;
+use iter::{AsErrorRef, Chain};
+
+/// Extension methods for wrapping the error of a `Result`.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use errors::ResultExt;
+///
+/// let res: Result<(), _> = Err("cat hair in generator");
+/// let err = res.context("ship exploded").unwrap_err();
+///
+/// assert_eq!(err.to_string(), "ship exploded");
+/// assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
+/// ```
+pub trait ResultExt<T> {
+    /// Wrap the error value with an additional message.
+    ///
+    /// This is equivalent to `result.map_err(|e| errors::wrap(msg, e))`.
+    fn context<D>(self, msg: D) -> Result<T, impl Error>
+    where
+        D: fmt::Debug + fmt::Display + Send + Sync + 'static;
+
+    /// Wrap the error value with a lazily constructed message.
+    ///
+    /// The closure is only called if the `Result` is an `Err`.
+    fn with_context<D, F>(self, f: F) -> Result<T, impl Error>
+    where
+        D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+        F: FnOnce() -> D;
+
+    /// Make the error value [opaque](::opaque), hiding its source chain.
+    ///
+    /// This is equivalent to `result.map_err(errors::opaque)`.
+    fn opaque(self) -> Result<T, impl Error>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: Into<BoxError>,
+{
+    #[track_caller]
+    fn context<D>(self, msg: D) -> Result<T, impl Error>
+    where
+        D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(t) => Ok(t),
+            Err(e) => Err(::new::wrap(msg, e)),
+        }
+    }
+
+    #[track_caller]
+    fn with_context<D, F>(self, f: F) -> Result<T, impl Error>
+    where
+        D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+        F: FnOnce() -> D,
+    {
+        match self {
+            Ok(t) => Ok(t),
+            Err(e) => Err(::new::wrap_with(f, e)),
+        }
+    }
+
+    fn opaque(self) -> Result<T, impl Error> {
+        self.map_err(::new::opaque)
+    }
+}
+
+/// Extension methods for inspecting and formatting any error.
+///
+/// These are the same as the functions in [`errors::iter`](::iter) and
+/// [`errors::fmt`](::fmt), as methods.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use errors::ErrorExt;
+///
+/// let err = errors::wrap("ship exploded", io::Error::other("cat hair in generator"));
+///
+/// assert!(err.find::<io::Error>().is_some());
+/// assert_eq!(err.report().to_string(), "ship exploded: cat hair in generator");
+/// ```
+pub trait ErrorExt: Error {
+    /// Create a [`Report`] to print this error and its source chain.
+    fn report(&self) -> Report<'_>;
+
+    /// Get an `Iterator` of the whole chain of errors.
+    ///
+    /// This is the same as [`errors::iter::chain`](::iter::chain).
+    fn chain_iter(&self) -> Chain<'_>;
+
+    /// Find the first occurrence of a type in the source chain.
+    ///
+    /// This is the same as [`errors::find`](::find).
+    fn find<E: Error + 'static>(&self) -> Option<&E>;
+}
+
+impl<T> ErrorExt for T
+where
+    T: Error + AsErrorRef + ?Sized,
+{
+    fn report(&self) -> Report<'_> {
+        Report::new(self.as_error_ref())
+    }
+
+    fn chain_iter(&self) -> Chain<'_> {
+        ::iter::chain(self)
+    }
+
+    fn find<E: Error + 'static>(&self) -> Option<&E> {
+        ::iter::find(self.as_error_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use std::cell::Cell;
+
+    use core::error::Error;
+    use super::{ErrorExt, ResultExt};
+
+    #[test]
+    fn context_passes_ok_through() {
+        let res: Result<u8, &str> = Ok(5);
+        assert_eq!(res.context("ship exploded").unwrap(), 5);
+
+        let called = Cell::new(false);
+        let res: Result<u8, &str> = Ok(5);
+        let res = res.with_context(|| {
+            called.set(true);
+            "ship exploded"
+        });
+        assert_eq!(res.unwrap(), 5);
+        assert!(!called.get());
+    }
+
+    #[test]
+    fn context_wraps_err() {
+        let res: Result<(), &str> = Err("cat hair in generator");
+        let err = res.context("ship exploded").unwrap_err();
+
+        assert_eq!(err.to_string(), "ship exploded");
+        assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
+        assert_eq!(format!("{:+}", err), "ship exploded: cat hair in generator");
+    }
+
+    #[test]
+    fn opaque_hides_source() {
+        let res: Result<u8, &str> = Ok(5);
+        assert_eq!(res.opaque().unwrap(), 5);
+
+        let res: Result<(), _> = Err(::wrap("ship exploded", "cat hair in generator"));
+        let err = res.opaque().unwrap_err();
+
+        assert_eq!(err.to_string(), "ship exploded");
+        assert!(err.source().is_none());
+        assert_eq!(format!("{:+}", err), "ship exploded: cat hair in generator");
+    }
+
+    #[test]
+    fn with_context_wraps_err() {
+        let res: Result<(), &str> = Err("cat hair in generator");
+        let err = res.with_context(|| format!("ship {}", "exploded")).unwrap_err();
+
+        assert_eq!(err.to_string(), "ship exploded");
+        assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
+    }
+
+    #[test]
+    fn error_ext() {
+        use std::io;
+
+        let err = ::wrap("ship exploded", io::Error::other("cat hair in generator"));
+        assert_eq!(err.report().to_string(), "ship exploded: cat hair in generator");
+        assert_eq!(
+            err.report().pretty(true).to_string(),
+            "ship exploded\nCaused by: cat hair in generator"
+        );
+        assert_eq!(err.chain_iter().count(), 2);
+        assert_eq!(err.find::<io::Error>().unwrap().to_string(), "cat hair in generator");
+
+        let err = io::Error::other(::wrap("b", "a"));
+        assert_eq!(err.report().to_string(), "b: a");
+        assert_eq!(err.chain_iter().count(), 2);
+        assert!(err.find::<io::Error>().is_some());
+        assert!(err.find::<core::fmt::Error>().is_none());
+
+        // trait objects too
+        let err: ::BoxError = ::wrap_boxed("b", io::Error::other("a"));
+        assert_eq!(err.report().to_string(), "b: a");
+        assert_eq!(err.chain_iter().count(), 2);
+        assert!(err.find::<io::Error>().is_some());
+    }
+}
@@ -1,7 +1,15 @@
 //! Utilities for formatting `Error`s.
 
-use std::fmt as std_fmt;
-use super::{BoxError, Error};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::fmt as core_fmt;
+use core::error::Error;
+use super::ErrorRef;
+use new::Opaque;
+#[cfg(feature = "std")]
+use super::BoxError;
 
 /// An adapter to pretty-print an error source chain.
 ///
@@ -15,48 +23,1080 @@ use super::{BoxError, Error};
 ///     Ok(())
 /// }
 /// ```
-pub struct Main(BoxError);
+///
+/// # Exit Codes
+///
+/// A `Main` also carries the exit code the process should report, which
+/// defaults to `1`. Since the runtime always exits with `1` when `main`
+/// returns an `Err`, use the `Termination` impl of `Main` directly to report
+/// a custom code:
+///
+/// ```no_run
+/// use std::process::{ExitCode, Termination};
+///
+/// fn main() -> ExitCode {
+///     match run() {
+///         Ok(()) => ExitCode::SUCCESS,
+///         Err(err) => err.report(),
+///     }
+/// }
+///
+/// fn run() -> Result<(), errors::Main> {
+///     Err(errors::Main::with_code("config not found", 78))
+/// }
+/// ```
+///
+/// # Backtraces
+///
+/// A `Main` prints the whole chain like `{:+#}`, so with the `backtrace`
+/// feature, the backtrace captured by each error of this crate is printed
+/// after its frame, including that of the deepest one. Backtraces of other
+/// errors can't be found yet, since `Error::provide` is still unstable.
+///
+/// # Depth
+///
+/// If the `ERRORS_MAX_DEPTH` environment variable is set to a number `N`, a
+/// `Main` prints at most `N` sources below the top error, same as `{:+#.N}`,
+/// so that a huge chain can be capped without changing the program.
+///
+/// # JSON
+///
+/// With the `json` feature, if the `ERRORS_FORMAT` environment variable is
+/// `json`, a `Main` is printed as a single line JSON object instead, with its
+/// keys sorted, such as
+/// `{"causes":["cat hair in generator"],"error":"ship exploded"}`. Each
+/// message is the same as in the human output. Returning from `main` still
+/// prefixes it with `Error: `, while `report()` prints only the JSON.
+#[cfg(feature = "std")]
+pub struct Main {
+    err: BoxError,
+    code: u8,
+    #[cfg(feature = "log")]
+    logged: bool,
+}
+
+#[cfg(feature = "std")]
+impl Main {
+    /// Create a `Main` that reports the given exit code.
+    ///
+    /// The code is a `u8`, rather than the `i32` of `std::process::exit`,
+    /// since that is what `ExitCode::from` takes on stable Rust. Exit codes
+    /// are only 8 bits on Unix anyways, so an `i32` would have to be silently
+    /// truncated.
+    pub fn with_code<E: Into<BoxError>>(err: E, code: u8) -> Main {
+        Main {
+            err: err.into(),
+            code,
+            #[cfg(feature = "log")]
+            logged: false,
+        }
+    }
+
+    /// Create a `Main` that also logs the error when it is printed.
+    ///
+    /// When the runtime prints the `Debug` of this `Main`, the chain is
+    /// first logged with [`errors::log_error`](::log_error), so the final
+    /// error reaches both `stderr` and any log aggregation.
+    ///
+    /// *Requires the `log` feature.*
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn main() -> Result<(), errors::Main> {
+    ///     Err(errors::Main::logged("ruh roh"))
+    /// }
+    /// ```
+    #[cfg(feature = "log")]
+    pub fn logged<E: Into<BoxError>>(err: E) -> Main {
+        Main {
+            logged: true,
+            ..Main::with_code(err, 1)
+        }
+    }
+
+    /// Create a `Main` that wraps `err` with a final message.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn main() -> Result<(), errors::Main> {
+    ///     let config = std::fs::read_to_string("ship.toml")
+    ///         .map_err(|e| errors::Main::context("failed to load config", e))?;
+    ///     # drop(config);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn context<D, E>(msg: D, err: E) -> Main
+    where
+        D: core_fmt::Debug + core_fmt::Display + Send + Sync + 'static,
+        E: Into<BoxError>,
+    {
+        Main::with_code(::new::wrap_boxed(msg, err), 1)
+    }
+
+    /// Create a `Main` from a message that isn't an error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// fn main() -> Result<(), errors::Main> {
+    ///     if std::env::args().len() < 2 {
+    ///         return Err(errors::Main::msg("missing ship name"));
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn msg<D>(msg: D) -> Main
+    where
+        D: core_fmt::Debug + core_fmt::Display + Send + Sync + 'static,
+    {
+        Main::with_code(::new::new_boxed(msg), 1)
+    }
+
+    /// Get the exit code this `Main` reports.
+    pub fn code(&self) -> u8 {
+        self.code
+    }
+}
+
+#[cfg(feature = "std")]
+impl core_fmt::Debug for Main {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        #[cfg(feature = "log")]
+        {
+            if self.logged {
+                ::new::log_error(&*self.err);
+            }
+        }
+
+        #[cfg(feature = "json")]
+        {
+            if json_format() {
+                return self.fmt_json(f);
+            }
+        }
+
+        match max_depth() {
+            Some(max) => write!(f, "{:+#.*}", max, fmt(&*self.err)),
+            None => core_fmt::Debug::fmt(&fmt(&*self.err), f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn max_depth() -> Option<usize> {
+    let max: usize = ::std::env::var("ERRORS_MAX_DEPTH").ok()?.trim().parse().ok()?;
+    // A precision must fit in a `u16`, and no chain is deeper anyways.
+    Some(max.min(u16::MAX.into()))
+}
+
+#[cfg(feature = "json")]
+impl Main {
+    fn fmt_json(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        // The same messages as the human output, each on its own.
+        let mut messages = Vec::new();
+        let mut chain = ::iter::chain(&*self.err).printed();
+        while let Some(err) = chain.next() {
+            // An `Opaque` prints the messages of the chain it hides
+            if let Some(op) = err.downcast_ref::<Opaque>() {
+                chain = ::iter::chain(op.hidden()).printed();
+                continue;
+            }
+            messages.push(format!("{:-}", err));
+        }
+
+        let mut messages = messages.into_iter();
+        let error = messages.next().unwrap_or_default();
+        let causes = messages
+            .take(max_depth().unwrap_or(usize::MAX))
+            .collect::<Vec<String>>();
+        let json = ::serde_json::json!({
+            "error": error,
+            "causes": causes,
+        });
+        core_fmt::Display::fmt(&json, f)
+    }
+}
+
+#[cfg(feature = "json")]
+fn json_format() -> bool {
+    ::std::env::var_os("ERRORS_FORMAT").is_some_and(|v| v == "json")
+}
+
+#[cfg(feature = "std")]
+impl<E: Into<BoxError>> From<E> for Main {
+    fn from(err: E) -> Main {
+        Main::with_code(err, 1)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::process::Termination for Main {
+    fn report(self) -> ::std::process::ExitCode {
+        #[cfg(feature = "json")]
+        {
+            if json_format() {
+                eprintln!("{:?}", self);
+                return ::std::process::ExitCode::from(self.code);
+            }
+        }
+
+        eprintln!("Error: {:?}", self);
+        ::std::process::ExitCode::from(self.code)
+    }
+}
+
+/// Create a `Display` adapter that applies the formatting rules to any error.
+///
+/// The adapter also implements `Debug`, which prints the whole source chain
+/// with traces, the same as `{:+#}`.
+///
+/// In the alternate form, `err` is formatted with `{:#}`, the same as each of
+/// its sources, so an error created by this crate prints its own frame. It
+/// is then formatted the same through the adapter as it would be directly.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let orig = errors::wrap("exploded", "cat hair in generator");
+/// let err = io::Error::new(io::ErrorKind::Other, orig);
+///
+/// // Foreign type might not know how to format sources...
+/// // But now it does!
+/// assert_eq!(
+///     format!("{:+}", errors::fmt(&err)),
+///     "exploded: cat hair in generator"
+/// );
+/// ```
+pub fn fmt<'a>(err: &'a dyn Error) -> impl core_fmt::Display + core_fmt::Debug + 'a {
+    Fmt(err)
+}
+
+/// Create a `Display` adapter that applies the formatting rules to any error.
+///
+/// This is the same as [`errors::fmt`](fmt()), with a name that reads better
+/// when called as `errors::fmt::chain`.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let orig = errors::wrap("exploded", "cat hair in generator");
+/// let err = io::Error::other(orig);
+///
+/// // Foreign type might not know how to format sources...
+/// // But now it does!
+/// assert_eq!(
+///     format!("{:+}", errors::fmt::chain(&err)),
+///     "exploded: cat hair in generator"
+/// );
+/// ```
+pub fn chain<'a>(err: &'a dyn Error) -> impl core_fmt::Display + core_fmt::Debug + 'a {
+    Fmt(err)
+}
+
+struct Fmt<'a>(&'a dyn Error);
+
+impl<'a> core_fmt::Display for Fmt<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        core_fmt::Display::fmt(&::new::wrap_ref(self.0), f)
+    }
+}
+
+impl<'a> core_fmt::Debug for Fmt<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        write!(f, "{:+#}", ::new::wrap_ref(self.0))
+    }
+}
+
+/// An error printer configured with methods instead of format flags.
+///
+/// By default, a `Report` prints the whole chain on one line, the same as
+/// `{:+}`. Like [`errors::fmt`](fmt()), it works with any error. Its `Debug`
+/// prints the same as its `Display`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+///
+/// let report = errors::Report::new(&err).pretty(true);
+///
+/// assert_eq!(report.to_string(), "ship exploded\nCaused by: cat hair in generator");
+/// ```
+pub struct Report<'a> {
+    error: &'a dyn Error,
+    pretty: bool,
+    show_backtrace: bool,
+}
+
+impl<'a> Report<'a> {
+    /// Create a `Report` for an error.
+    pub fn new(error: &'a dyn Error) -> Report<'a> {
+        Report {
+            error,
+            pretty: false,
+            show_backtrace: false,
+        }
+    }
+
+    /// Print each source on its own line, after `Caused by: `.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Print where each error was created, and any backtrace, the same as
+    /// `{:+#}`.
+    ///
+    /// Since the frames are printed below each message, this implies
+    /// [`pretty`](Report::pretty).
+    pub fn show_backtrace(mut self, show_backtrace: bool) -> Self {
+        self.show_backtrace = show_backtrace;
+        self
+    }
+}
+
+impl<'a> core_fmt::Display for Report<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        if self.show_backtrace {
+            return write!(f, "{:+#}", ::new::wrap_ref(self.error));
+        }
+        if !self.pretty {
+            return write!(f, "{:+}", ::new::wrap_ref(self.error));
+        }
+
+        write!(f, "{:-}", self.error)?;
+        let mut sources = ::iter::sources(self.error).printed();
+        while let Some(err) = sources.next() {
+            // An `Opaque` prints the messages of the chain it hides
+            if let Some(op) = err.downcast_ref::<Opaque>() {
+                sources = ::iter::chain(op.hidden()).printed();
+                continue;
+            }
+            write!(f, "\nCaused by: {:-}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> core_fmt::Debug for Report<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        core_fmt::Display::fmt(self, f)
+    }
+}
+
+/// Options to print the whole chain of any error.
+///
+/// By default, the chain is printed on one line, the same as `{:+}` with
+/// [`errors::fmt`](fmt()). Each option changes the output the same as the
+/// adapter or flag of the same purpose, so they can all be combined in one
+/// place.
+///
+/// # Example
+///
+/// ```
+/// use errors::fmt::Options;
+///
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+///
+/// let opts = Options::new().separator(" >> ").max_depth(1);
+///
+/// assert_eq!(opts.render(&err).to_string(), "c >> b");
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Options<'a> {
+    separator: &'a str,
+    caused_by: &'a str,
+    max_depth: Option<usize>,
+    alternate: bool,
+    #[cfg(feature = "color")]
+    color: Color,
+}
+
+impl<'a> Options<'a> {
+    /// Create the default `Options`.
+    pub fn new() -> Options<'a> {
+        Options {
+            separator: ": ",
+            caused_by: ::new::CAUSED_BY,
+            max_depth: None,
+            alternate: false,
+            #[cfg(feature = "color")]
+            color: Color::Never,
+        }
+    }
+
+    /// Join the messages with `separator`, instead of `": "`.
+    ///
+    /// The same as [`errors::fmt::with_separator`](with_separator).
+    pub fn separator(mut self, separator: &'a str) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Label each source with `label`, instead of `"Caused by: "`, when
+    /// [`alternate`](Options::alternate).
+    ///
+    /// The same as [`errors::fmt::with_caused_by`](with_caused_by).
+    pub fn caused_by(mut self, label: &'a str) -> Self {
+        self.caused_by = label;
+        self
+    }
+
+    /// Print at most `max` sources below the top error, the same as
+    /// `{:+.N}`.
+    ///
+    /// If not set, the precision of the formatter is used, if any.
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Print each source on its own line, along with where each error was
+    /// created, the same as `{:+#}`.
+    pub fn alternate(mut self, alternate: bool) -> Self {
+        self.alternate = alternate;
+        self
+    }
+
+    /// When to print with ANSI colors.
+    ///
+    /// The same as [`errors::fmt::colored`](colored), and only used when
+    /// [`alternate`](Options::alternate). Defaults to [`Color::Never`].
+    ///
+    /// *Requires the `color` feature.*
+    #[cfg(feature = "color")]
+    pub fn color(mut self, when: Color) -> Self {
+        self.color = when;
+        self
+    }
+
+    /// Create a `Display` adapter that prints an error with these options.
+    pub fn render<'b>(&self, err: &'b dyn Error) -> impl core_fmt::Display + 'b
+    where
+        'a: 'b,
+    {
+        Rendered {
+            err,
+            opts: *self,
+        }
+    }
+}
+
+impl<'a> Default for Options<'a> {
+    fn default() -> Options<'a> {
+        Options::new()
+    }
+}
+
+struct Rendered<'a> {
+    err: &'a dyn Error,
+    opts: Options<'a>,
+}
+
+impl<'a> Rendered<'a> {
+    fn write_to<W: core_fmt::Write>(&self, w: &mut W, max: Option<usize>) -> core_fmt::Result {
+        let err = ::new::wrap_ref_labeled(self.err, self.opts.separator, self.opts.caused_by);
+        // A precision must fit in a `u16`, and no chain is deeper anyways.
+        let max = max.map(|max| max.min(u16::MAX.into()));
+        match (self.opts.alternate, max) {
+            (false, None) => write!(w, "{:+}", err),
+            (true, None) => write!(w, "{:+#}", err),
+            (false, Some(max)) => write!(w, "{:+.*}", max, err),
+            (true, Some(max)) => write!(w, "{:+#.*}", max, err),
+        }
+    }
+}
+
+impl<'a> core_fmt::Display for Rendered<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        let max = self.opts.max_depth.or(f.precision());
+
+        #[cfg(feature = "color")]
+        {
+            if self.opts.alternate && self.opts.color.enabled() {
+                let mut out = String::new();
+                self.write_to(&mut out, max)?;
+                return write_colored(f, &out, self.opts.caused_by.trim_end());
+            }
+        }
+
+        self.write_to(f, max)
+    }
+}
+
+/// Returns whether two errors have the same message chain.
+///
+/// Errors are considered equal if their messages, and the messages of each
+/// error in their source chains, are the same. That is, they are compared by
+/// their `{:+}` output with [`errors::fmt`](fmt()).
+///
+/// # Example
+///
+/// ```
+/// let a = errors::wrap("exploded", "cat hair in generator");
+/// let b = errors::wrap("exploded", errors::new("cat hair in generator"));
+///
+/// assert!(errors::eq(&a, &b));
+/// assert!(!errors::eq(&a, &errors::new("exploded")));
+/// ```
+pub fn eq(a: &dyn Error, b: &dyn Error) -> bool {
+    format!("{:+}", fmt(a)) == format!("{:+}", fmt(b))
+}
+
+/// Create a `Display` adapter that joins the message chain with `separator`.
+///
+/// This follows the same formatting rules as [`errors::fmt`](fmt()), except
+/// that `separator` is used instead of `": "` when printing the message chain
+/// (`{:+}`). The alternate form (`{:+#}`) still puts each source on its own
+/// line.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("exploded", "cat hair in generator");
+///
+/// assert_eq!(
+///     format!("{:+}", errors::fmt::with_separator(&err, " >> ")),
+///     "exploded >> cat hair in generator"
+/// );
+/// ```
+pub fn with_separator<'a>(err: &'a dyn Error, separator: &'a str) -> impl core_fmt::Display + 'a {
+    ::new::wrap_ref_separated(err, separator)
+}
+
+/// Create a `Display` adapter that labels each source with `label`.
+///
+/// This follows the same formatting rules as [`errors::fmt`](fmt()), except
+/// that `label` is used instead of `"Caused by: "` before each source in the
+/// alternate form (`{:+#}`), such as to translate it.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("exploded", "cat hair in generator");
+/// let out = format!("{:+#}", errors::fmt::with_caused_by(&err, "Verursacht durch: "));
+///
+/// assert!(out.ends_with("\nVerursacht durch: cat hair in generator"));
+/// ```
+pub fn with_caused_by<'a>(err: &'a dyn Error, label: &'a str) -> impl core_fmt::Display + 'a {
+    ::new::wrap_ref_labeled(err, ": ", label)
+}
+
+/// Create a `Display` adapter that prints the whole chain on a single line.
+///
+/// This prints the same as `{:+#}`, including any frames, but with each new
+/// line replaced by `" | "`, for log collectors that expect one line per
+/// entry. The precision flag still limits the chain.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+/// let line = errors::fmt::oneline(&err).to_string();
+///
+/// assert!(line.starts_with("ship exploded | at "));
+/// assert!(line.ends_with(" | Caused by: cat hair in generator"));
+/// ```
+pub fn oneline<'a>(err: &'a dyn Error) -> impl core_fmt::Display + 'a {
+    Oneline(err)
+}
+
+struct Oneline<'a>(&'a dyn Error);
+
+impl<'a> core_fmt::Display for Oneline<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        use core::fmt::Write;
+
+        let err = ::new::wrap_ref(self.0);
+        let mut line = Flatten {
+            f,
+            line_start: false,
+        };
+        match line.f.precision() {
+            Some(max) => write!(line, "{:+#.*}", max, err),
+            None => write!(line, "{:+#}", err),
+        }
+    }
+}
+
+/// Replaces new lines, and the indentation after them, with `" | "`.
+struct Flatten<'a, 'b: 'a> {
+    f: &'a mut core_fmt::Formatter<'b>,
+    line_start: bool,
+}
+
+impl<'a, 'b> core_fmt::Write for Flatten<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> core_fmt::Result {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.write_line(first)?;
+        }
+        for line in lines {
+            self.f.write_str(" | ")?;
+            self.line_start = true;
+            self.write_line(line)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'b> Flatten<'a, 'b> {
+    fn write_line(&mut self, mut line: &str) -> core_fmt::Result {
+        if self.line_start {
+            line = line.trim_start_matches(' ');
+            self.line_start = line.is_empty();
+        }
+        self.f.write_str(line)
+    }
+}
+
+/// Create a `Display` adapter that prints a limited message chain.
+///
+/// This prints the message chain like `{:+.N}` with `max` as the precision,
+/// followed by how many messages were left out, if any.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("d", errors::wrap("c", errors::wrap("b", "a")));
+///
+/// assert_eq!(errors::fmt::truncated(&err, 1).to_string(), "d: c ... (2 more)");
+/// assert_eq!(errors::fmt::truncated(&err, 3).to_string(), "d: c: b: a");
+/// ```
+pub fn truncated<'a>(err: &'a dyn Error, max: usize) -> impl core_fmt::Display + 'a {
+    Truncated { err, max }
+}
+
+struct Truncated<'a> {
+    err: &'a dyn Error,
+    max: usize,
+}
+
+impl<'a> core_fmt::Display for Truncated<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        let err = ::new::wrap_ref(self.err);
+        // A precision must fit in a `u16`, and no chain is deeper anyways.
+        let max = self.max.min(u16::MAX.into());
+        if f.alternate() {
+            write!(f, "{:+#.*}", max, err)?;
+        } else {
+            write!(f, "{:+.*}", max, err)?;
+        }
+
+        let hidden = printed_len(self.err).saturating_sub(self.max.saturating_add(1));
+        if hidden > 0 {
+            write!(f, " ... ({} more)", hidden)?;
+        }
+        Ok(())
+    }
+}
+
+/// Append the message chain of an error to a `String`.
+///
+/// This writes the same as `{:+}` of [`errors::fmt`](fmt()), or `{:+#}` if
+/// `alternate`, with `max` as the precision, directly into `buf`. Reusing the
+/// same `buf` for many errors avoids allocating a new `String` each time.
+///
+/// # Example
+///
+/// ```
+/// let mut buf = String::new();
+///
+/// for err in &[errors::wrap("b", "a"), errors::wrap("d", "c")] {
+///     errors::render_into(&mut buf, err, false, None);
+///     buf.push('\n');
+/// }
+///
+/// assert_eq!(buf, "b: a\nd: c\n");
+/// ```
+pub fn render_into(buf: &mut String, err: &dyn Error, alternate: bool, max: Option<usize>) {
+    let opts = Options::new().alternate(alternate);
+    Rendered { err, opts }
+        .write_to(buf, max)
+        .expect("writing to a String doesn't fail");
+}
+
+/// Write the message chain of an error to an `io::Write`.
+///
+/// This writes the same as `{:+}` of [`errors::fmt`](fmt()), or `{:+#}` if
+/// `alternate`, straight into `w`, such as `stderr` or a socket, without
+/// building a `String` first.
+///
+/// *Requires the `std` feature.*
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+///
+/// errors::write_chain(&mut std::io::stderr(), &err, false).unwrap();
+/// ```
+#[cfg(feature = "std")]
+pub fn write_chain<W>(w: &mut W, err: &dyn Error, alternate: bool) -> ::std::io::Result<()>
+where
+    W: ::std::io::Write + ?Sized,
+{
+    write!(w, "{}", Options::new().alternate(alternate).render(err))
+}
+
+/// The number of messages printed by `{:+}`, which includes any chain
+/// hidden by an `Opaque`.
+fn printed_len(err: &dyn Error) -> usize {
+    let mut len = 1;
+    for err in ::iter::sources(err).printed() {
+        if let Some(op) = err.downcast_ref::<Opaque>() {
+            return len + printed_len(op.hidden());
+        }
+        len += 1;
+    }
+    len
+}
+
+/// Create a `Display` adapter that prints the source chain root-first.
+///
+/// Each message of the chain is joined by `" <- "`, which can be changed with
+/// [`Reversed::joiner`]. The precision flag (`{:.N}`) limits how many
+/// sources below the top message are included, same as with `{:+.N}`.
+///
+/// Since source chains can only be walked from the top, this collects the
+/// chain into a `Vec` each time it is formatted.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+///
+/// assert_eq!(
+///     errors::fmt::reversed(&err).to_string(),
+///     "cat hair in generator <- ship exploded"
+/// );
+/// ```
+pub fn reversed<'a>(err: &'a ErrorRef) -> Reversed<'a> {
+    Reversed {
+        err,
+        joiner: " <- ",
+    }
+}
+
+/// A `Display` adapter that prints the source chain root-first.
+///
+/// Created with [`errors::fmt::reversed`](reversed).
+pub struct Reversed<'a> {
+    err: &'a ErrorRef,
+    joiner: &'a str,
+}
 
-impl std_fmt::Debug for Main {
-    fn fmt(&self, f: &mut std_fmt::Formatter) -> std_fmt::Result {
-        let err = crate::new::wrap_ref(&*self.0);
-        write!(f, "{:+#}", err)
+impl<'a> Reversed<'a> {
+    /// Set the string used to join each message of the chain.
+    pub fn joiner(mut self, joiner: &'a str) -> Self {
+        self.joiner = joiner;
+        self
     }
 }
 
-impl<E: Into<BoxError>> From<E> for Main {
-    fn from(err: E) -> Main {
-        Main(err.into())
+impl<'a> core_fmt::Display for Reversed<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        let max = f.precision().unwrap_or(usize::MAX);
+        let mut chain = ::iter::chain(self.err)
+            .printed()
+            .take(max.saturating_add(1))
+            .collect::<Vec<_>>();
+        // The root kept by `errors::opaque_keep_root` is printed with the
+        // hidden chain.
+        if let Some(i) = chain.iter().position(|e| e.is::<Opaque>()) {
+            chain.truncate(i + 1);
+        }
+
+        for (i, err) in chain.iter().rev().enumerate() {
+            if i > 0 {
+                f.write_str(self.joiner)?;
+            }
+
+            // An `Opaque` still prints its hidden chain, in its normal order
+            if err.is::<Opaque>() {
+                write!(f, "{:+}", err)?;
+            } else {
+                write!(f, "{:-}", err)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
-/// Create a `Display` adapter that applies the formatting rules to any error.
+/// Create a `Display` adapter that redacts each message of the chain.
+///
+/// The message chain is printed like `{:+}`, or with each source on its own
+/// line like `{:+#}` but without frames, and `redactor` is applied to every
+/// message on its own before it is written. This way, it can't match across
+/// the joiners, nor change them. An [`Opaque`](::Opaque) error still prints
+/// its hidden chain, which is redacted the same.
 ///
 /// # Example
 ///
 /// ```
-/// use std::io;
+/// use std::borrow::Cow;
 ///
-/// let orig = errors::wrap("exploded", "cat hair in generator");
-/// let err = io::Error::new(io::ErrorKind::Other, orig);
+/// let err = errors::wrap("login failed for ann@example.com", "password expired");
+/// let redacted = errors::fmt::redacted(&err, |msg| {
+///     if msg.contains('@') {
+///         Cow::Owned(msg.replace("ann@example.com", "[email]"))
+///     } else {
+///         Cow::Borrowed(msg)
+///     }
+/// });
+///
+/// assert_eq!(redacted.to_string(), "login failed for [email]: password expired");
+/// ```
+pub fn redacted<'a, F>(err: &'a ErrorRef, redactor: F) -> impl core_fmt::Display + 'a
+where
+    F: Fn(&str) -> Cow<'_, str> + 'a,
+{
+    Redacted { err, redactor }
+}
+
+struct Redacted<'a, F> {
+    err: &'a ErrorRef,
+    redactor: F,
+}
+
+impl<'a, F> core_fmt::Display for Redacted<'a, F>
+where
+    F: Fn(&str) -> Cow<'_, str>,
+{
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        use core::fmt::Write;
+
+        let joiner = if f.alternate() { "\nCaused by: " } else { ": " };
+        let mut msg = String::new();
+        let mut chain = ::iter::chain(self.err).printed();
+        let mut first = true;
+        while let Some(err) = chain.next() {
+            // An `Opaque` prints the message of the error it hides
+            if let Some(op) = err.downcast_ref::<Opaque>() {
+                chain = ::iter::chain(op.hidden()).printed();
+                continue;
+            }
+
+            if !first {
+                f.write_str(joiner)?;
+            }
+            first = false;
+
+            msg.clear();
+            write!(msg, "{:-}", err)?;
+            f.write_str(&(self.redactor)(&msg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Create a `Display` adapter that prints each message of the chain on its
+/// own numbered line.
+///
+/// Each line is `{i}: {message}`, starting at `0` for `err` itself, which is
+/// handy for pasting into bug reports. Like [`errors::iter::chain`](::iter::chain),
+/// it stops at an [`Opaque`](::Opaque) error, printing only its top message.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
 ///
-/// // Foreign type might not know how to format sources...
-/// // But now it does!
 /// assert_eq!(
-///     format!("{:+}", errors::fmt(&err)),
-///     "exploded: cat hair in generator"
+///     errors::fmt::numbered(&err).to_string(),
+///     "0: ship exploded\n1: cat hair in generator"
+/// );
+/// ```
+pub fn numbered<'a>(err: &'a ErrorRef) -> impl core_fmt::Display + 'a {
+    Numbered(err)
+}
+
+struct Numbered<'a>(&'a ErrorRef);
+
+impl<'a> core_fmt::Display for Numbered<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        for (i, err) in ::iter::chain(self.0).printed().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}: {:-}", i, err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Create a `Display` adapter that prints the chain as an indented tree.
+///
+/// Each source is printed on its own line, one level deeper than the error
+/// it is the source of. Like [`errors::iter::chain`](::iter::chain), it stops
+/// at an [`Opaque`](::Opaque) error, printing only its top message.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", errors::wrap("cat hair in generator", "insufficient lint filters"));
+///
+/// assert_eq!(
+///     errors::fmt::tree(&err).to_string(),
+///     "\
+/// ship exploded
+/// └─ caused by: cat hair in generator
+///    └─ caused by: insufficient lint filters"
 /// );
 /// ```
-pub fn fmt<'a>(err: &'a dyn Error) -> impl std_fmt::Display + 'a {
-    ::new::wrap_ref(err)
+pub fn tree<'a>(err: &'a ErrorRef) -> impl core_fmt::Display + 'a {
+    Tree(err)
+}
+
+struct Tree<'a>(&'a ErrorRef);
+
+impl<'a> core_fmt::Display for Tree<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        for (depth, err) in ::iter::chain(self.0).printed().enumerate() {
+            if depth > 0 {
+                f.write_str("\n")?;
+                for _ in 1..depth {
+                    f.write_str("   ")?;
+                }
+                f.write_str("└─ caused by: ")?;
+            }
+            write!(f, "{:-}", err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Create a `Display` adapter that prints the chain on one line, with where
+/// each error was created.
+///
+/// This prints like `{:+}`, appending ` (file:line)` to each message of an
+/// error created by this crate. Errors from elsewhere don't know where they
+/// were created, so their messages are printed as is. Like
+/// [`errors::iter::chain`](::iter::chain), it stops at an
+/// [`Opaque`](::Opaque) error, printing only its top message.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+///
+/// // ship exploded (src/main.rs:2): cat hair in generator (src/main.rs:2)
+/// println!("{}", errors::fmt::located_inline(&err));
+/// ```
+pub fn located_inline<'a>(err: &'a ErrorRef) -> impl core_fmt::Display + 'a {
+    LocatedInline(err)
+}
+
+struct LocatedInline<'a>(&'a ErrorRef);
+
+impl<'a> core_fmt::Display for LocatedInline<'a> {
+    fn fmt(&self, f: &mut core_fmt::Formatter) -> core_fmt::Result {
+        for (i, err) in ::iter::chain(self.0).printed().enumerate() {
+            if i > 0 {
+                f.write_str(": ")?;
+            }
+            write!(f, "{:-}", err)?;
+            if let Some(location) = ::new::location_of(err) {
+                write!(f, " ({})", location)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Create a `Display` adapter that prints the chain with ANSI colors.
+///
+/// This prints the same as `{:+#}` with [`errors::fmt`](fmt()), with the top
+/// message in bold and each `Caused by:` label dimmed. The precision flag
+/// still limits the chain.
+///
+/// *Requires the `color` feature.*
+///
+/// # Example
+///
+/// ```
+/// use errors::fmt::Color;
+///
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+///
+/// eprintln!("{}", errors::fmt::colored(&err, Color::Auto));
+/// ```
+#[cfg(feature = "color")]
+pub fn colored<'a>(err: &'a dyn Error, when: Color) -> impl core_fmt::Display + 'a {
+    Options::new().alternate(true).color(when).render(err)
+}
+
+/// When [`errors::fmt::colored`](colored) uses colors.
+///
+/// *Requires the `color` feature.*
+#[cfg(feature = "color")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// Only if `stderr` is a terminal, and the `NO_COLOR` environment
+    /// variable isn't set.
+    Auto,
+    /// Always use colors.
+    Always,
+    /// Never use colors.
+    Never,
+}
+
+#[cfg(feature = "color")]
+impl Color {
+    fn enabled(self) -> bool {
+        use std::io::IsTerminal;
+
+        match self {
+            Color::Auto => {
+                ::std::env::var_os("NO_COLOR").is_none() && ::std::io::stderr().is_terminal()
+            }
+            Color::Always => true,
+            Color::Never => false,
+        }
+    }
+}
+
+/// Writes the `{:+#}` output in `out` with the top message in bold, and
+/// each `label` dimmed.
+#[cfg(feature = "color")]
+fn write_colored(f: &mut core_fmt::Formatter, out: &str, label: &str) -> core_fmt::Result {
+    const BOLD: &str = "\x1b[1m";
+    const DIM: &str = "\x1b[2m";
+    const RESET: &str = "\x1b[0m";
+
+    for (i, line) in out.split('\n').enumerate() {
+        if i == 0 {
+            write!(f, "{}{}{}", BOLD, line, RESET)?;
+            continue;
+        }
+
+        f.write_str("\n")?;
+        match line.strip_prefix(label) {
+            Some(rest) if !label.is_empty() => write!(f, "{}{}{}{}", DIM, label, RESET, rest)?,
+            _ => f.write_str(line)?,
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
     use std::fmt;
     use std::io;
 
-    use {BoxError, Error};
+    use core::error::Error;
+    use BoxError;
 
     #[derive(Debug)]
     struct Naive(Option<BoxError>);
@@ -73,7 +1113,393 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn main_exit_code() {
+        let main = ::Main::from("ruh roh");
+        assert_eq!(main.code(), 1);
+
+        let main = ::Main::with_code("ruh roh", 78);
+        assert_eq!(main.code(), 78);
+        assert_eq!(format!("{:?}", main), "ruh roh");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn main_msg() {
+        struct Msg;
+
+        impl fmt::Debug for Msg {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("Msg")
+            }
+        }
+
+        impl fmt::Display for Msg {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("missing ship name")
+            }
+        }
+
+        let (main, line) = (::Main::msg(Msg), line!());
+        assert_eq!(main.code(), 1);
+
+        // Like `errors::new`, the frame of the caller is included.
+        let out = format!("{:?}", main);
+        let frame = format!("missing ship name\n    at {}:{}", file!(), line);
+        assert!(out.starts_with(&frame), "{}", out);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn main_context() {
+        let main = ::Main::context("ship exploded", ::wrap("cat hair", "in generator"));
+        assert_eq!(main.code(), 1);
+
+        let out = format!("{:?}", main);
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("ship exploded"));
+        assert!(out.contains("\nCaused by: cat hair\n"));
+        assert!(out.contains("\nCaused by: in generator"));
+    }
+
+    #[test]
+    fn eq() {
+        let a = ::wrap("c", ::wrap("b", "a"));
+
+        assert!(super::eq(&a, &a));
+        assert!(super::eq(&a, &::wrap("c", ::wrap("b", ::new("a")))));
+
+        // foreign errors
+        let naive = Naive(Some(::wrap("b", "a").into()));
+        assert!(super::eq(&naive, &::wrap("naive", ::wrap("b", "a"))));
+
+        // differing depths
+        assert!(!super::eq(&a, &::wrap("c", "b")));
+        assert!(!super::eq(&a, &::wrap("c", ::wrap("b", ::wrap("a", "z")))));
+
+        // differing messages
+        assert!(!super::eq(&a, &::wrap("c", ::wrap("B", "a"))));
+        assert!(!super::eq(&a, &::wrap("c", ::wrap("b", "A"))));
+    }
+
+    #[test]
+    fn oneline() {
+        let (err, line) = (::wrap("c", ::wrap("b", io::Error::other("a"))), line!());
+
+        let out = super::oneline(&err).to_string();
+        assert!(!out.contains('\n'), "{}", out);
+        if !cfg!(any(feature = "backtrace", feature = "time")) {
+            assert_eq!(
+                out,
+                format!(
+                    "c | at {f}:{l} | Caused by: b | at {f}:{l} | Caused by: a",
+                    f = file!(),
+                    l = line,
+                ),
+            );
+        }
+        for msg in &["c", "Caused by: b", "Caused by: a"] {
+            assert!(out.contains(msg), "{}", out);
+        }
+
+        let out = format!("{:.1}", super::oneline(&err));
+        assert!(out.contains("Caused by: b") && !out.contains("Caused by: a"), "{}", out);
+    }
+
+    #[test]
+    fn with_separator() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+
+        let sep = |s| super::with_separator(&err, s);
+        assert_eq!(format!("{}", sep(" >> ")), "c");
+        assert_eq!(format!("{:+}", sep(" >> ")), "c >> b >> a");
+        assert_eq!(format!("{:+.1}", sep(" >> ")), "c >> b");
+        assert_eq!(format!("{:+}", sep(" → ")), "c → b → a");
+        assert_eq!(format!("{:+}", sep("")), "cba");
+
+        // separator is kept through an opaque segment
+        let err = ::wrap("c", ::opaque(::wrap("b", "a")));
+        let sep = |s| super::with_separator(&err, s);
+        assert_eq!(format!("{:+}", sep(" >> ")), "c >> b >> a");
+        assert_eq!(format!("{:+.1}", sep(" >> ")), "c >> b");
+    }
+
+    #[test]
+    fn with_caused_by() {
+        let err = ::wrap("b", "a");
+        let label = |l| super::with_caused_by(&err, l);
+        let alt = format!("{:+#}", err);
+        assert!(alt.contains("\nCaused by: a"));
+        assert_eq!(format!("{:+#}", label("Verursacht durch: ")), alt.replace("Caused by: ", "Verursacht durch: "));
+        assert_eq!(format!("{:+#}", label("")), alt.replace("Caused by: ", ""));
+        assert_eq!(format!("{:+#}", label("Caused by: ")), alt);
+        // only the alternate form is labeled
+        assert_eq!(format!("{:+}", label("caused by -> ")), "b: a");
+
+        // the label is kept through an opaque segment, and when indenting
+        let err = ::wrap("c", ::opaque(::wrap("b", "a")));
+        let label = |l| super::with_caused_by(&err, l);
+        assert_eq!(
+            format!("{:+#}", label("<- ")),
+            format!("{:+#}", err).replace("Caused by: ", "<- ")
+        );
+        assert_eq!(
+            format!("{:+#2}", label("<- ")),
+            format!("{:+#2}", err).replace("Caused by: ", "<- ")
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_chain() {
+        let err = ::wrap("c", ::wrap("b", io::Error::other("a")));
+        let mut buf = Vec::new();
+
+        super::write_chain(&mut buf, &err, false).unwrap();
+        assert_eq!(String::from_utf8(buf.clone()).unwrap(), "c: b: a");
+
+        buf.clear();
+        super::write_chain(&mut buf, &err, true).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), format!("{:+#}", super::fmt(&err)));
+
+        // foreign errors follow along
+        let err = io::Error::other(::wrap("b", "a"));
+        let mut buf = Vec::new();
+        super::write_chain(&mut buf, &err, false).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), format!("{:+}", super::fmt(&err)));
+    }
+
+    #[test]
+    fn options() {
+        use super::Options;
+
+        let err = ::wrap("c", ::wrap("b", io::Error::other("a")));
+
+        assert_eq!(Options::new().render(&err).to_string(), format!("{:+}", super::fmt(&err)));
+        assert_eq!(
+            Options::default().alternate(true).render(&err).to_string(),
+            format!("{:+#}", super::fmt(&err))
+        );
+        assert_eq!(
+            Options::new().separator(" >> ").render(&err).to_string(),
+            format!("{:+}", super::with_separator(&err, " >> "))
+        );
+        assert_eq!(
+            Options::new().alternate(true).caused_by("Verursacht durch: ").render(&err).to_string(),
+            format!("{:+#}", super::with_caused_by(&err, "Verursacht durch: "))
+        );
+        assert_eq!(
+            Options::new().separator(" > ").max_depth(1).render(&err).to_string(),
+            "c > b"
+        );
+        assert_eq!(
+            Options::new().alternate(true).max_depth(0).render(&err).to_string(),
+            format!("{:+#.0}", super::fmt(&err))
+        );
+
+        // the precision is used without a max_depth
+        assert_eq!(format!("{:.1}", Options::new().render(&err)), "c: b");
+        assert_eq!(format!("{:.1}", Options::new().max_depth(0).render(&err)), "c");
+
+        // a max_depth past what a precision can hold is no limit
+        assert_eq!(Options::new().max_depth(100_000).render(&err).to_string(), "c: b: a");
+        assert_eq!(
+            Options::new().alternate(true).max_depth(usize::MAX).render(&err).to_string(),
+            format!("{:+#}", super::fmt(&err))
+        );
+
+        // foreign errors follow along
+        let err = io::Error::other(::wrap("b", "a"));
+        assert_eq!(Options::new().render(&err).to_string(), "b: a");
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn options_color() {
+        use super::{Color, Options};
+
+        let err = ::wrap("b", "a");
+        let opts = Options::new().alternate(true);
+
+        assert_eq!(
+            opts.color(Color::Always).render(&err).to_string(),
+            super::colored(&err, Color::Always).to_string()
+        );
+        assert_eq!(
+            opts.color(Color::Never).render(&err).to_string(),
+            format!("{:+#}", super::fmt(&err))
+        );
+        // colors need each source on its own line
+        assert_eq!(opts.alternate(false).color(Color::Always).render(&err).to_string(), "b: a");
+
+        let colored = opts.caused_by("Cause: ").color(Color::Always).render(&err).to_string();
+        assert!(colored.ends_with("\n\x1b[2mCause:\x1b[0m a"), "{:?}", colored);
+    }
+
+    #[test]
+    fn render_into() {
+        use alloc::string::String;
+
+        let err = ::wrap("c", ::wrap("b", io::Error::other("a")));
+        let mut buf = String::new();
+
+        super::render_into(&mut buf, &err, false, None);
+        assert_eq!(buf, format!("{:+}", super::fmt(&err)));
+
+        buf.clear();
+        super::render_into(&mut buf, &err, false, Some(1));
+        super::render_into(&mut buf, &err, true, None);
+        super::render_into(&mut buf, &err, true, Some(0));
+        let expected = format!(
+            "{:+.1}{:+#}{:+#.0}",
+            super::fmt(&err),
+            super::fmt(&err),
+            super::fmt(&err),
+        );
+        assert_eq!(buf, expected);
+
+        // any max is taken, even past what a precision can hold
+        buf.clear();
+        super::render_into(&mut buf, &err, false, Some(usize::MAX));
+        assert_eq!(buf, "c: b: a");
+    }
+
+    #[test]
+    fn truncated() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+
+        assert_eq!(super::truncated(&err, 0).to_string(), "c ... (2 more)");
+        assert_eq!(super::truncated(&err, 1).to_string(), "c: b ... (1 more)");
+        assert_eq!(super::truncated(&err, 2).to_string(), "c: b: a");
+        assert_eq!(super::truncated(&err, 5).to_string(), "c: b: a");
+
+        let err = ::new("a");
+        assert_eq!(super::truncated(&err, 0).to_string(), "a");
+
+        // counts what is hidden behind an opaque
+        let err = ::wrap("d", ::opaque(::wrap("c", ::wrap("b", "a"))));
+        assert_eq!(super::truncated(&err, 1).to_string(), "d: c ... (2 more)");
+        assert_eq!(super::truncated(&err, 3).to_string(), "d: c: b: a");
+
+        // no limit
+        assert_eq!(super::truncated(&err, usize::MAX).to_string(), "d: c: b: a");
+    }
+
+    #[test]
+    fn reversed() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+
+        assert_eq!(format!("{:+}", err), "c: b: a");
+        assert_eq!(super::reversed(&err).to_string(), "a <- b <- c");
+        assert_eq!(format!("{:.0}", super::reversed(&err)), "c");
+        assert_eq!(format!("{:.1}", super::reversed(&err)), "b <- c");
+        assert_eq!(
+            super::reversed(&err).joiner(" > ").to_string(),
+            "a > b > c"
+        );
+
+        let err = ::new("a");
+        assert_eq!(super::reversed(&err).to_string(), "a");
+    }
+
+    #[test]
+    fn numbered() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+        assert_eq!(super::numbered(&err).to_string(), "0: c\n1: b\n2: a");
+
+        let err = ::wrap("c", ::opaque(::wrap("b", "a")));
+        assert_eq!(super::numbered(&err).to_string(), "0: c\n1: b");
+
+        let err = io::Error::other("a");
+        assert_eq!(super::numbered(&err).to_string(), "0: a");
+    }
+
+    #[test]
+    fn located_inline() {
+        let line = line!() + 1;
+        let err = ::wrap("c", ::wrap("b", io::Error::other("a")));
+        let here = format!("{}:{}", file!(), line);
+        assert_eq!(
+            super::located_inline(&err).to_string(),
+            format!("c ({0}): b ({0}): a", here),
+        );
+
+        let line = line!() + 1;
+        let err = ::wrap("b", ::opaque(::wrap("a", "z")));
+        let here = format!("{}:{}", file!(), line);
+        assert_eq!(
+            super::located_inline(&err).to_string(),
+            format!("b ({0}): a ({0})", here),
+        );
+
+        let err = io::Error::other("a");
+        assert_eq!(super::located_inline(&err).to_string(), "a");
+    }
+
+    #[test]
+    fn tree() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+        assert_eq!(
+            super::tree(&err).to_string(),
+            "c\n└─ caused by: b\n   └─ caused by: a",
+        );
+
+        let err = ::wrap("d", ::wrap("c", ::opaque(::wrap("b", "a"))));
+        assert_eq!(
+            super::tree(&err).to_string(),
+            "d\n└─ caused by: c\n   └─ caused by: b",
+        );
+
+        assert_eq!(super::tree(&::new("a")).to_string(), "a");
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn colored() {
+        use super::Color;
+
+        let err = ::wrap("b", "a");
+        let plain = format!("{:+#}", super::fmt(&err));
+        assert_eq!(super::colored(&err, Color::Never).to_string(), plain);
+
+        let colored = super::colored(&err, Color::Always).to_string();
+        assert!(colored.starts_with("\x1b[1mb\x1b[0m\n"), "{:?}", colored);
+        assert!(colored.ends_with("\n\x1b[2mCaused by:\x1b[0m a"), "{:?}", colored);
+        assert_eq!(colored.replace("\x1b[1m", "").replace("\x1b[2m", "").replace("\x1b[0m", ""), plain);
+    }
+
+    #[test]
+    fn redacted() {
+        use std::borrow::Cow;
+
+        fn redact(msg: &str) -> Cow<'_, str> {
+            match msg.find('@') {
+                Some(at) => Cow::Owned(format!("{}@[redacted]", &msg[..at])),
+                None => Cow::Borrowed(msg),
+            }
+        }
+
+        let err = ::wrap("user ann@example.com", ::wrap("mail to bob@example.com", "refused"));
+        assert_eq!(
+            super::redacted(&err, redact).to_string(),
+            "user ann@[redacted]: mail to bob@[redacted]: refused",
+        );
+        assert_eq!(
+            format!("{:#}", super::redacted(&err, redact)),
+            "user ann@[redacted]\nCaused by: mail to bob@[redacted]\nCaused by: refused",
+        );
+
+        // Each message is redacted alone, never across the joiner
+        let err = ::wrap("a", "@b");
+        assert_eq!(super::redacted(&err, redact).to_string(), "a: @[redacted]");
+
+        let err = ::wrap("c", ::opaque(::wrap("b", io::Error::other("a@b"))));
+        assert_eq!(super::redacted(&err, redact).to_string(), "c: b: a@[redacted]");
+    }
+
     #[test]
+    #[allow(clippy::io_other_error)]
     fn chain_wraps_outside_errors() {
         let a = "a";
 
@@ -95,6 +1521,87 @@ mod tests {
         assert_eq!(format!("{:+.1}", super::fmt(&err)), naive_a);
     }
 
+    #[test]
+    fn chain_max_outside_errors() {
+        #[derive(Debug)]
+        struct Custom(&'static str, BoxError);
+
+        impl fmt::Display for Custom {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.0)
+            }
+        }
+
+        impl Error for Custom {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&*self.1)
+            }
+        }
+
+        let root = io::Error::other("disk on fire");
+        let err = Custom("load failed", Box::new(Custom("read failed", Box::new(root))));
+        let err = super::fmt(&err);
+
+        assert_eq!(format!("{:.1}", err), "load failed");
+        assert_eq!(format!("{:+.0}", err), "load failed");
+        assert_eq!(format!("{:+.1}", err), "load failed: read failed");
+        assert_eq!(format!("{:+.2}", err), "load failed: read failed: disk on fire");
+        assert_eq!(format!("{:+.3}", err), "load failed: read failed: disk on fire");
+        assert_eq!(format!("{:+#.1}", err), "load failed\nCaused by: read failed");
+
+        // below one of ours
+        let root = io::Error::other("disk on fire");
+        let err = ::wrap("start failed", Custom("load failed", Box::new(root)));
+        assert_eq!(format!("{:+.1}", err), "start failed: load failed");
+        assert_eq!(format!("{:+.2}", err), "start failed: load failed: disk on fire");
+    }
+
+    #[test]
+    fn debug_is_alternate_chain() {
+        let err = io::Error::other(::wrap("b", "a"));
+        assert_eq!(
+            format!("{:?}", super::fmt(&err)),
+            format!("{:+#}", super::fmt(&err))
+        );
+
+        let err = ::wrap("c", ::wrap("b", "a"));
+        assert_eq!(
+            format!("{:?}", super::fmt(&err)),
+            format!("{:+#}", super::fmt(&err))
+        );
+        assert!(format!("{:?}", super::fmt(&err)).contains("\nCaused by: a"));
+    }
+
+    #[test]
+    fn report() {
+        use super::Report;
+
+        let err = ::wrap("c", ::wrap("b", "a"));
+        assert_eq!(Report::new(&err).to_string(), "c: b: a");
+        assert_eq!(Report::new(&err).pretty(true).to_string(), "c\nCaused by: b\nCaused by: a");
+        assert_eq!(
+            Report::new(&err).pretty(true).pretty(false).to_string(),
+            "c: b: a"
+        );
+        assert_eq!(
+            Report::new(&err).show_backtrace(true).to_string(),
+            format!("{:+#}", err)
+        );
+        assert_eq!(
+            Report::new(&err).pretty(true).show_backtrace(true).to_string(),
+            format!("{:+#}", err)
+        );
+        assert_eq!(format!("{:?}", Report::new(&err)), "c: b: a");
+
+        // foreign errors follow along
+        let err = io::Error::other(::wrap("b", "a"));
+        assert_eq!(Report::new(&err).to_string(), "b: a");
+        assert_eq!(Report::new(&err).pretty(true).to_string(), "b\nCaused by: a");
+
+        let err = ::wrap("c", ::opaque(::wrap("b", "a")));
+        assert_eq!(Report::new(&err).pretty(true).to_string(), "c\nCaused by: b\nCaused by: a");
+    }
+
     #[test]
     fn chain_wraps_our_errors() {
         let err = ::wrap("b", "a");
@@ -107,6 +1614,19 @@ mod tests {
         assert_eq!(format!("{:+.1}", super::fmt(&err)), b_a);
     }
 
+    #[test]
+    fn chain_alternate_keeps_our_frames() {
+        let (err, line) = (::wrap("b", "a"), line!());
+        let frame = format!("b\n    at {}:{}", file!(), line);
+        assert!(format!("{:#}", super::fmt(&err)).starts_with(&frame));
+        assert_eq!(format!("{:+#}", super::fmt(&err)), format!("{:+#}", err));
+        assert_eq!(format!("{:+}", super::fmt(&err)), "b: a");
+
+        // foreign errors have no frame
+        let err = io::Error::other("a");
+        assert_eq!(format!("{:+#}", super::fmt(&err)), "a");
+    }
+
     /// Simulate an error type that by default prefers to show one level
     /// deep in its source chain, but wants to opt-in to behaving correctly
     /// with `errors::fmt`.
@@ -152,7 +1672,14 @@ mod tests {
         let orig = ::new("a");
         let one_deep = ::opaque(OneDeep(orig.into()));
 
-        assert_eq!(format!("{}", one_deep), "one deep: a");
+        // like an error without a source, only the top message
+        assert_eq!(format!("{}", one_deep), "one deep");
+        assert_eq!(format!("{:+}", one_deep), "one deep: a");
+
+        // the top of the hidden chain still prints its frame
+        let err = ::opaque(::wrap("b", "a"));
+        assert_eq!(format!("{:#}", err), format!("{:+#.0}", err));
+        assert!(format!("{:#}", err).starts_with(&format!("b\n    at {}:", file!())));
 
         let err = ::wrap("b", one_deep);
         let b = "b";
@@ -2,22 +2,71 @@
 //!
 //! This contains tools for inspecting error `source` chains.
 //!
-//! There are two `Iterator`s:
+//! The `Iterator`s are:
 //!
 //! - [`chain`](iter::chain): Iterates over the source chain, including the
 //!   first `Error`.
 //! - [`sources`](iter::sources): Iterates over only the sources of an `Error`,
 //!   excluding itself.
+//! - [`messages`](iter::messages): Iterates over the message of each error in
+//!   the source chain, including the first `Error`.
+//! - [`chain_rev`](iter::chain_rev): Iterates over the whole chain, starting
+//!   from the root.
+//! - [`indexed`](iter::indexed): Iterates over the whole chain, with the depth
+//!   of each error and whether it is the root.
+//! - [`pairs`](iter::pairs): Iterates over each error in the whole chain,
+//!   paired with its source.
+//! - [`chain_send`](iter::chain_send): Iterates over the chain as
+//!   `Send + Sync` errors, as far as they are created by this crate.
+//! - [`chain_through_opaque`](iter::chain_through_opaque): Iterates over the
+//!   whole chain, including the sources hidden by [`Opaque`](::Opaque) errors.
+//!   This is only meant for diagnostics tooling.
 //!
 //! There also a few utilities for quickly traversing a source chain with a
 //! specific goal in mind.
 //!
 //! - [`root`](iter::root): Finds the root source for a given error.
+//! - [`root_or`](iter::root_or): Finds the deepest occurance of a type in a
+//!   source chain, or else the root source.
+//! - [`depth`](iter::depth): Counts the number of errors in a source chain.
 //! - [`is`](iter::is): Checks a source chain if it contains a given type.
 //! - [`find`](iter::find): Finds the first occurance of a type in a source
 //!   chain.
+//! - [`is_within`](iter::is_within) and [`find_within`](iter::find_within):
+//!   Like `is` and `find`, but only look at the top of a source chain.
+//! - [`find_last`](iter::find_last): Finds the last occurance of a type in a
+//!   source chain.
+//! - [`all_of`](iter::all_of): Finds every occurance of a type in a source
+//!   chain.
+//! - [`find_map`](iter::find_map): Finds the first value extracted from an
+//!   error in a source chain.
+//! - [`position`](iter::position): Finds how deep in a source chain a type
+//!   first occurs.
+//! - [`try_fold`](iter::try_fold): Accumulates a value over a source chain,
+//!   possibly stopping early.
+//! - [`any`](iter::any): Checks if any error in a source chain matches a
+//!   predicate.
+//! - [`all`](iter::all): Checks if every error in a source chain matches a
+//!   predicate.
+//! - [`join`](iter::join): Joins the message of each error in the source
+//!   chain into one `String`.
+//! - [`contains_message`](iter::contains_message): Checks if any message in
+//!   a source chain contains a substring.
+//! - [`fingerprint`](iter::fingerprint): Hashes the messages of a source
+//!   chain, such as to deduplicate reports.
+//! - [`best`](iter::best): Finds the error in a source chain with the
+//!   highest rank.
+//! - [`type_counts`](iter::type_counts): Counts how many times each of some
+//!   types occurs in a source chain.
 
-use super::{Error, ErrorRef};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::ops::ControlFlow;
+use super::{ErrorRef, SendErrorRef};
+use new::Opaque;
+use Source;
 
 /// Get an `Iterator` of the whole chain of errors.
 ///
@@ -34,8 +83,62 @@ use super::{Error, ErrorRef};
 ///     assert_eq!(err.to_string(), s);
 /// }
 /// ```
-pub fn chain<'a>(err: &'a ErrorRef) -> impl Iterator<Item = &'a ErrorRef> + 'a {
-    Iter { err: Some(err) }
+///
+/// Any error type can be passed, including trait objects such as a
+/// `dyn Error + Send + Sync`. Generic code that also accepts trait objects
+/// can use the [`AsErrorRef`](iter::AsErrorRef) bound:
+///
+/// ```
+/// use std::error::Error;
+/// use errors::iter::AsErrorRef;
+///
+/// fn depth<E: AsErrorRef + ?Sized>(err: &E) -> usize {
+///     errors::iter::chain(err).count()
+/// }
+///
+/// let err: Box<dyn Error + Send + Sync> = errors::wrap_boxed("b", "a");
+/// assert_eq!(depth(&*err), 2);
+/// assert_eq!(depth(&std::io::Error::other("a")), 1);
+/// ```
+pub fn chain<'a, E>(err: &'a E) -> Chain<'a>
+where
+    E: AsErrorRef + ?Sized,
+{
+    Chain::new(Some(err.as_error_ref()))
+}
+
+/// Types that can be viewed as a `&(dyn Error + 'static)`.
+///
+/// This lets [`chain`](iter::chain) accept a reference to any error, sized or
+/// not. It is implemented for every `Error + 'static` type, and for the
+/// `dyn Error` trait objects.
+pub trait AsErrorRef {
+    /// View this as a `&(dyn Error + 'static)`.
+    fn as_error_ref(&self) -> &ErrorRef;
+}
+
+impl<E: Error + 'static> AsErrorRef for E {
+    fn as_error_ref(&self) -> &ErrorRef {
+        self
+    }
+}
+
+impl AsErrorRef for dyn Error + 'static {
+    fn as_error_ref(&self) -> &ErrorRef {
+        self
+    }
+}
+
+impl AsErrorRef for dyn Error + Send + 'static {
+    fn as_error_ref(&self) -> &ErrorRef {
+        self
+    }
+}
+
+impl AsErrorRef for dyn Error + Send + Sync + 'static {
+    fn as_error_ref(&self) -> &ErrorRef {
+        self
+    }
 }
 
 /// Get an `Iterator` of the source chain of this error.
@@ -54,8 +157,11 @@ pub fn chain<'a>(err: &'a ErrorRef) -> impl Iterator<Item = &'a ErrorRef> + 'a {
 ///     assert_eq!(err.to_string(), s);
 /// }
 /// ```
-pub fn sources(err: &dyn Error) -> impl Iterator<Item = &ErrorRef> {
-    Iter { err: err.source() }
+pub fn sources(err: &dyn Error) -> Chain<'_> {
+    Chain {
+        top: false,
+        ..Chain::new(err.source())
+    }
 }
 
 /// Returns whether the error source chain contains a given type.
@@ -72,7 +178,266 @@ pub fn sources(err: &dyn Error) -> impl Iterator<Item = &ErrorRef> {
 /// ```
 pub fn find<E: Error + 'static>(err: &ErrorRef) -> Option<&E> {
     chain(err)
-        .find_map(|e| e.downcast_ref::<E>())
+        .find_map(|e| ::new::typed(e).downcast_ref::<E>())
+}
+
+/// Finds the last occurrence of a type in the error source chain.
+///
+/// Unlike [`find`](iter::find), which returns the shallowest match, this
+/// returns the deepest one, closest to the root cause.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let inner = io::Error::other("disk on fire");
+/// let err = io::Error::other(errors::wrap("read failed", inner));
+///
+/// let found = errors::iter::find_last::<io::Error>(&err).unwrap();
+/// assert_eq!(found.to_string(), "disk on fire");
+/// ```
+pub fn find_last<E: Error + 'static>(err: &ErrorRef) -> Option<&E> {
+    chain(err)
+        .filter_map(|e| ::new::typed(e).downcast_ref::<E>())
+        .last()
+}
+
+/// Get an `Iterator` of every error of a type in the source chain.
+///
+/// Equivalent to `chain(err).filter_map(|e| e.downcast_ref::<E>())`, except
+/// that an error with fields attached by [`errors::with_field`](::with_field),
+/// or made by [`errors::attach`](::attach), is checked as the error it holds.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let inner = io::Error::from_raw_os_error(2);
+/// let err = io::Error::new(io::ErrorKind::Other, errors::wrap("read failed", inner));
+///
+/// let codes = errors::iter::all_of::<io::Error>(&err)
+///     .map(|e| e.raw_os_error())
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(codes, [None, Some(2)]);
+/// ```
+pub fn all_of<E: Error + 'static>(err: &ErrorRef) -> impl Iterator<Item = &E> {
+    chain(err)
+        .filter_map(|e| ::new::typed(e).downcast_ref::<E>())
+}
+
+/// Get an `Iterator` of the message of each error in the whole chain.
+///
+/// Equivalent to `chain(err).map(|e| e.to_string())`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+///
+/// let msgs = errors::iter::messages(&err).collect::<Vec<_>>();
+///
+/// assert_eq!(msgs.join(" <- "), "c <- b <- a");
+/// ```
+pub fn messages<'a>(err: &'a ErrorRef) -> impl Iterator<Item = String> + 'a {
+    chain(err)
+        .map(|e| e.to_string())
+}
+
+/// Join the message of each error in the whole chain into one `String`.
+///
+/// Equivalent to `messages(err).collect::<Vec<_>>().join(sep)`, without
+/// collecting each message into its own `String` first.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+///
+/// assert_eq!(errors::iter::join(&err, " -> "), "c -> b -> a");
+/// ```
+pub fn join(err: &ErrorRef, sep: &str) -> String {
+    use core::fmt::Write;
+
+    let mut joined = String::new();
+    for (i, e) in chain(err).enumerate() {
+        if i > 0 {
+            joined.push_str(sep);
+        }
+        write!(joined, "{}", e).expect("writing to a String doesn't fail");
+    }
+    joined
+}
+
+/// Get an `Iterator` of the whole chain of errors, including hidden sources.
+///
+/// Like [`chain`](iter::chain), but when an [`Opaque`](::Opaque) error is
+/// found, iteration continues into the error it hides. The `Opaque` itself
+/// is skipped, since it has the same message as the error it hides.
+///
+/// **This is an escape hatch for diagnostics only**, such as crash reporters
+/// that want to record everything. An error is made opaque precisely so that
+/// code does *not* react to what is inside, so application logic should
+/// always use [`chain`](iter::chain) instead.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::opaque(errors::wrap("b", "a")));
+///
+/// assert_eq!(errors::iter::chain(&err).count(), 2);
+/// assert_eq!(errors::iter::chain_through_opaque(&err).count(), 3);
+/// ```
+pub fn chain_through_opaque<'a>(err: &'a ErrorRef) -> Chain<'a> {
+    Chain {
+        through_opaque: true,
+        ..Chain::new(Some(err))
+    }
+}
+
+/// Get an `Iterator` of the whole chain of errors, starting from the root.
+///
+/// Includes the `err` in the iterator as the last item.
+///
+/// Source chains can only be walked from the top, so this collects the chain
+/// into a `Vec` before iterating, costing an allocation.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+///
+/// let expected = ["a", "b", "c"];
+///
+/// for (err, &s) in errors::iter::chain_rev(&err).zip(expected.iter()) {
+///     assert_eq!(err.to_string(), s);
+/// }
+/// ```
+pub fn chain_rev<'a>(err: &'a ErrorRef) -> impl Iterator<Item = &'a ErrorRef> + 'a {
+    chain(err)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+}
+
+/// Get an `Iterator` of the whole chain of errors, with their depth.
+///
+/// Each item is `(depth, is_root, err)`, where the depth of `err` itself is
+/// `0`, and `is_root` is only `true` for the last error in the chain. This is
+/// handy for indenting, or styling the root cause differently.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+///
+/// let lines = errors::iter::indexed(&err)
+///     .map(|(i, is_root, e)| {
+///         let mark = if is_root { "└" } else { "├" };
+///         format!("{:width$}{} {}", "", mark, e, width = i * 2)
+///     })
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(lines, ["├ c", "  ├ b", "    └ a"]);
+/// ```
+pub fn indexed<'a>(err: &'a ErrorRef) -> impl Iterator<Item = (usize, bool, &'a ErrorRef)> + 'a {
+    let mut chain = chain(err).enumerate().peekable();
+    core::iter::from_fn(move || {
+        let (i, e) = chain.next()?;
+        Some((i, chain.peek().is_none(), e))
+    })
+}
+
+/// Get an `Iterator` of each error in the whole chain paired with its source.
+///
+/// Each item is `(parent, child)`, where `child` is the `source` of `parent`,
+/// so a chain of `n` errors has `n - 1` pairs. This is handy for describing
+/// how one error led to the next.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", errors::wrap("generator failed", "cat hair"));
+///
+/// let lines = errors::iter::pairs(&err)
+///     .map(|(parent, child)| format!("{} because {}", parent, child))
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(lines, [
+///     "ship exploded because generator failed",
+///     "generator failed because cat hair",
+/// ]);
+/// ```
+pub fn pairs<'a>(err: &'a ErrorRef) -> impl Iterator<Item = (&'a ErrorRef, &'a ErrorRef)> + 'a {
+    let mut chain = chain(err).peekable();
+    core::iter::from_fn(move || {
+        let parent = chain.next()?;
+        let child = *chain.peek()?;
+        Some((parent, child))
+    })
+}
+
+/// Get an `Iterator` of the chain of errors that are `Send + Sync`.
+///
+/// Like [`chain`](iter::chain), but each error can be sent to or shared with
+/// other threads.
+///
+/// # Limitations
+///
+/// `Error::source` returns a `&(dyn Error + 'static)`, which can't be made
+/// `Send + Sync` again, so this can only follow the sources of errors
+/// created by this crate, which are always kept as a `BoxError`. The chain
+/// ends at the first error that isn't from this crate, after yielding it,
+/// even if that error has sources of its own.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap("ship exploded", errors::wrap("generator failed", "cat hair"));
+///
+/// let messages = std::thread::scope(|s| {
+///     s.spawn(|| {
+///         errors::iter::chain_send(&err)
+///             .map(|e| e.to_string())
+///             .collect::<Vec<_>>()
+///     }).join().unwrap()
+/// });
+///
+/// assert_eq!(messages, ["ship exploded", "generator failed", "cat hair"]);
+/// ```
+pub fn chain_send<'a>(err: &'a SendErrorRef) -> impl Iterator<Item = &'a SendErrorRef> + 'a {
+    core::iter::successors(Some(err), |&err| ::new::source_send(err))
+}
+
+/// Returns the first value extracted from an error in the source chain.
+///
+/// Applies `f` to each error in `chain(err)`, stopping at the first one that
+/// returns `Some`.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err1 = io::Error::new(io::ErrorKind::NotFound, "boom");
+/// let err2 = errors::wrap("ruh roh", err1);
+///
+/// let kind = errors::iter::find_map(&err2, |e| {
+///     e.downcast_ref::<io::Error>().map(|io| io.kind())
+/// });
+///
+/// assert_eq!(kind, Some(io::ErrorKind::NotFound));
+/// ```
+pub fn find_map<'a, T, F>(err: &'a ErrorRef, f: F) -> Option<T>
+where
+    F: FnMut(&'a ErrorRef) -> Option<T>,
+{
+    chain(err)
+        .find_map(f)
 }
 
 /// Returns whether the error source chain contains a given type.
@@ -88,9 +453,278 @@ pub fn find<E: Error + 'static>(err: &ErrorRef) -> Option<&E> {
 /// let err2 = errors::wrap("ruh roh", err1);
 /// assert!(errors::is::<io::Error>(&err2));
 /// ```
+///
+/// A boxed error is passed by dereferencing the box, whether or not it is
+/// `Send + Sync`. Passing `&Box<dyn Error>` itself doesn't work, since a
+/// `Box<dyn Error>` is not an `Error`.
+///
+/// ```
+/// use std::error::Error;
+/// use std::io;
+///
+/// let err: Box<dyn Error> = Box::new(io::Error::other("boom"));
+/// assert!(errors::is::<io::Error>(&*err));
+/// ```
 pub fn is<E: Error + 'static>(err: &ErrorRef) -> bool {
     chain(err)
-        .any(|e| e.is::<E>())
+        .any(|e| ::new::typed(e).is::<E>())
+}
+
+/// Checks if the first `max_depth` errors of the source chain contain a
+/// given type.
+///
+/// Like [`is`](iter::is), but an error of the type buried deeper in the chain
+/// is ignored. `err` itself is at depth `0`, so a `max_depth` of `1` only
+/// checks `err`.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap("c", errors::wrap("b", io::Error::other("a")));
+///
+/// assert!(errors::iter::is_within::<io::Error>(&err, 3));
+/// assert!(!errors::iter::is_within::<io::Error>(&err, 2));
+/// ```
+pub fn is_within<E: Error + 'static>(err: &ErrorRef, max_depth: usize) -> bool {
+    chain(err)
+        .take(max_depth)
+        .any(|e| ::new::typed(e).is::<E>())
+}
+
+/// Finds the first occurrence of a type in the first `max_depth` errors of
+/// the source chain.
+///
+/// Like [`find`](iter::find), but an error of the type buried deeper in the
+/// chain is ignored, the same as [`is_within`](iter::is_within).
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap("c", io::Error::other("b"));
+///
+/// assert!(errors::iter::find_within::<io::Error>(&err, 2).is_some());
+/// assert!(errors::iter::find_within::<io::Error>(&err, 1).is_none());
+/// ```
+pub fn find_within<E: Error + 'static>(err: &ErrorRef, max_depth: usize) -> Option<&E> {
+    chain(err)
+        .take(max_depth)
+        .find_map(|e| ::new::typed(e).downcast_ref::<E>())
+}
+
+/// Returns the index in the source chain of the first error of a given type.
+///
+/// The index counts from `0` for `err` itself, like [`chain`](iter::chain),
+/// and matches the error that [`find`](iter::find) would return.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap("c", errors::wrap("b", io::Error::other("a")));
+///
+/// assert_eq!(errors::iter::position::<io::Error>(&err), Some(2));
+/// ```
+pub fn position<E: Error + 'static>(err: &ErrorRef) -> Option<usize> {
+    chain(err)
+        .position(|e| ::new::typed(e).is::<E>())
+}
+
+/// Accumulate a value over the source chain, possibly stopping early.
+///
+/// The closure is called with each error in the chain, including `err`
+/// itself, and returns `ControlFlow::Continue` with the accumulated value to
+/// keep going, or `ControlFlow::Break` to stop with it.
+///
+/// # Example
+///
+/// ```
+/// use std::ops::ControlFlow;
+///
+/// let err = errors::wrap("c", errors::wrap("b", errors::wrap("marker", "a")));
+///
+/// // Collect the messages up to the marker.
+/// let msgs = errors::iter::try_fold(&err, Vec::new(), |mut msgs, e| {
+///     let msg = e.to_string();
+///     if msg == "marker" {
+///         return ControlFlow::Break(msgs);
+///     }
+///     msgs.push(msg);
+///     ControlFlow::Continue(msgs)
+/// });
+///
+/// assert_eq!(msgs, ["c", "b"]);
+/// ```
+pub fn try_fold<B, F>(err: &ErrorRef, init: B, mut f: F) -> B
+where
+    F: FnMut(B, &ErrorRef) -> ControlFlow<B, B>,
+{
+    let mut acc = init;
+    for e in chain(err) {
+        match f(acc, e) {
+            ControlFlow::Continue(next) => acc = next,
+            ControlFlow::Break(done) => return done,
+        }
+    }
+    acc
+}
+
+/// Returns whether any error in the source chain matches a predicate.
+///
+/// Equivalent to `chain(err).any(f)`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::wrap("", "a"));
+///
+/// assert!(errors::iter::any(&err, |e| e.to_string().is_empty()));
+/// ```
+pub fn any<F>(err: &ErrorRef, f: F) -> bool
+where
+    F: FnMut(&ErrorRef) -> bool,
+{
+    chain(err)
+        .any(f)
+}
+
+/// Returns whether every error in the source chain matches a predicate.
+///
+/// Equivalent to `chain(err).all(f)`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+///
+/// assert!(errors::iter::all(&err, |e| e.to_string().len() == 1));
+/// ```
+pub fn all<F>(err: &ErrorRef, f: F) -> bool
+where
+    F: FnMut(&ErrorRef) -> bool,
+{
+    chain(err)
+        .all(f)
+}
+
+/// Returns whether the message of any error in the source chain contains a
+/// substring.
+///
+/// This is a last resort, for errors that can't be matched by type. Each
+/// message is formatted into a new `String` to search it, so this allocates
+/// once per error checked.
+///
+/// Equivalent to `chain(err).any(|e| e.to_string().contains(needle))`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("request failed", "connection reset by peer");
+///
+/// assert!(errors::iter::contains_message(&err, "reset"));
+/// ```
+pub fn contains_message(err: &ErrorRef, needle: &str) -> bool {
+    chain(err)
+        .any(|e| e.to_string().contains(needle))
+}
+
+/// Returns the error in the source chain with the highest rank.
+///
+/// `rank` scores each error, such as preferring known domain errors over
+/// generic wrappers. If several errors share the highest rank, the shallowest
+/// one is returned. Since the chain always includes `err`, there is always a
+/// result.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap("request failed", errors::wrap("read failed", io::Error::other("disk on fire")));
+///
+/// let best = errors::iter::best(&err, |e| if e.is::<io::Error>() { 10 } else { 0 });
+/// assert_eq!(best.to_string(), "disk on fire");
+/// ```
+pub fn best<F>(err: &ErrorRef, rank: F) -> &ErrorRef
+where
+    F: Fn(&ErrorRef) -> i32,
+{
+    let mut best = (err, rank(err));
+    for e in sources(err) {
+        let r = rank(e);
+        if r > best.1 {
+            best = (e, r);
+        }
+    }
+    best.0
+}
+
+/// Counts how many errors of each type are in the source chain.
+///
+/// A `dyn Error` can't tell its type, only whether it is some given one. So
+/// the types to look for are passed as `types`, made with
+/// [`ErrorType::of`](iter::ErrorType::of). Each type that occurs is yielded
+/// once with its count, in the order of `types`. Errors of any other type are
+/// not counted.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use std::num::ParseIntError;
+/// use errors::iter::ErrorType;
+///
+/// let err = errors::wrap("retry 2", io::Error::other(errors::wrap("retry 1", io::Error::other("timed out"))));
+/// let types = [ErrorType::of::<io::Error>(), ErrorType::of::<ParseIntError>()];
+///
+/// let counts = errors::iter::type_counts(&err, &types).collect::<Vec<_>>();
+/// assert_eq!(counts, [("std::io::error::Error", 2)]);
+/// ```
+pub fn type_counts<'a>(
+    err: &'a ErrorRef,
+    types: &'a [ErrorType],
+) -> impl Iterator<Item = (&'static str, usize)> + 'a {
+    types
+        .iter()
+        .map(move |ty| (ty.name, chain(err).filter(|&e| (ty.is)(e)).count()))
+        .filter(|&(_, count)| count > 0)
+}
+
+/// A type of error to count with [`type_counts`](iter::type_counts).
+#[derive(Clone, Copy)]
+pub struct ErrorType {
+    name: &'static str,
+    is: fn(&ErrorRef) -> bool,
+}
+
+impl ErrorType {
+    /// The error type `E`, named by `std::any::type_name`.
+    pub fn of<E: Error + 'static>() -> ErrorType {
+        ErrorType {
+            name: core::any::type_name::<E>(),
+            is: |err| ::new::typed(err).is::<E>(),
+        }
+    }
+
+    /// Use a different name for this type, such as a shorter one.
+    pub fn named(self, name: &'static str) -> ErrorType {
+        ErrorType { name, ..self }
+    }
+
+    /// The name of this type.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl fmt::Debug for ErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ErrorType").field(&self.name).finish()
+    }
 }
 
 /// Get the root source of an `Error`.
@@ -117,16 +751,872 @@ pub fn root(err: &ErrorRef) -> &ErrorRef {
         .expect("errors::iter::chain always yields at least 1 item")
 }
 
-struct Iter<'a> {
+/// Find the deepest error of a type in the chain, or else the root.
+///
+/// This is the most fundamental error that is understood, combining
+/// [`find_last`](iter::find_last) and [`root`](iter::root).
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap("c", io::Error::other(errors::wrap("b", "a")));
+/// assert_eq!(errors::iter::root_or::<io::Error>(&err).to_string(), "b");
+///
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+/// assert_eq!(errors::iter::root_or::<io::Error>(&err).to_string(), "a");
+/// ```
+pub fn root_or<E: Error + 'static>(err: &ErrorRef) -> &ErrorRef {
+    let mut found = None;
+    let mut last = err;
+    for e in chain(err) {
+        if ::new::typed(e).is::<E>() {
+            found = Some(::new::typed(e));
+        }
+        last = e;
+    }
+    found.unwrap_or(last)
+}
+
+/// Get the number of errors in the whole chain.
+///
+/// Includes the `err` itself, so this is always at least 1. Equivalent to
+/// `chain(err).count()`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("c", errors::wrap("b", "a"));
+///
+/// assert_eq!(errors::iter::depth(&err), 3);
+/// ```
+pub fn depth(err: &ErrorRef) -> usize {
+    chain(err).count()
+}
+
+/// Compute a fingerprint of an error, such as to deduplicate reports.
+///
+/// The fingerprint is a hash of the message of each error in the whole
+/// chain, including the chain hidden by an [`Opaque`](::Opaque), along with
+/// where each error created by this crate was created. It is based on what
+/// the errors say, not on which error values they are, so two errors made
+/// the same way fingerprint the same.
+///
+/// Since the locations include line numbers, editing the code can change
+/// the fingerprints. Use [`fingerprint_messages`](iter::fingerprint_messages)
+/// to leave them out.
+///
+/// The hash (64-bit FNV-1a) is the same on every platform and version of
+/// Rust, so fingerprints can be compared across processes. It is not
+/// cryptographic, and different errors may rarely collide.
+///
+/// # Example
+///
+/// ```
+/// let make = || errors::wrap("request failed", "timed out");
+///
+/// assert_eq!(errors::fingerprint(&make()), errors::fingerprint(&make()));
+/// ```
+pub fn fingerprint(err: &ErrorRef) -> u64 {
+    fingerprint_inner(err, true)
+}
+
+/// Compute a fingerprint of an error from its messages only.
+///
+/// This is the same as [`fingerprint`](iter::fingerprint), but where each
+/// error was created is not included, so the same messages always
+/// fingerprint the same.
+///
+/// # Example
+///
+/// ```
+/// let a = errors::wrap("request failed", "timed out");
+/// let b = errors::wrap("request failed", "timed out");
+///
+/// assert_eq!(errors::fingerprint_messages(&a), errors::fingerprint_messages(&b));
+/// ```
+pub fn fingerprint_messages(err: &ErrorRef) -> u64 {
+    fingerprint_inner(err, false)
+}
+
+fn fingerprint_inner(err: &ErrorRef, locations: bool) -> u64 {
+    use core::fmt::Write;
+
+    let mut hasher = Fnv1a::new();
+    for e in chain_through_opaque(err) {
+        write!(hasher, "{}", e).expect("Fnv1a doesn't fail");
+        if locations {
+            if let Some(location) = ::new::location_of(e) {
+                hasher.byte(0xfe);
+                write!(hasher, "{}", location).expect("Fnv1a doesn't fail");
+            }
+        }
+        // 0xff never appears in UTF-8, so messages can't run together.
+        hasher.byte(0xff);
+    }
+    hasher.0
+}
+
+// The 64-bit FNV-1a hash, which is tiny and stable, unlike `DefaultHasher`.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn byte(&mut self, b: u8) {
+        self.0 ^= u64::from(b);
+        self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+}
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            self.byte(b);
+        }
+        Ok(())
+    }
+}
+
+/// An `Iterator` over a source chain.
+///
+/// Created with [`chain`](iter::chain) or [`sources`](iter::sources). It is
+/// cheap to `Clone`, which can be used to restart iteration from the current
+/// position.
+///
+/// # Cycles
+///
+/// A buggy `Error` could return a `source` that eventually points back to
+/// itself. Rather than looping forever, the iterator stops once it sees the
+/// same error, by address and vtable, again. This is done without
+/// allocating, so some errors of the cycle may be yielded more than once
+/// before it is detected.
+#[derive(Clone)]
+pub struct Chain<'a> {
     err: Option<&'a ErrorRef>,
+    through_opaque: bool,
+    printed: bool,
+    // Whether `err` is the error the chain was created from.
+    top: bool,
+    // Brent's cycle detection: compare each error against a saved one,
+    // moving the saved one after every power of 2 steps.
+    saved: Option<&'a ErrorRef>,
+    power: usize,
+    steps: usize,
 }
 
-impl<'a> Iterator for Iter<'a> {
+impl<'a> Chain<'a> {
+    fn new(err: Option<&'a ErrorRef>) -> Chain<'a> {
+        Chain {
+            err,
+            through_opaque: false,
+            printed: false,
+            top: true,
+            saved: None,
+            power: 1,
+            steps: 1,
+        }
+    }
+
+    /// Stop before a [`Source`](::Source) below the top, since the error
+    /// above it already printed it in its own message.
+    pub(crate) fn printed(self) -> Chain<'a> {
+        Chain {
+            printed: true,
+            ..self
+        }
+    }
+}
+
+fn same(a: &ErrorRef, b: &ErrorRef) -> bool {
+    // An error shares its address with a source stored at offset 0, so the
+    // address alone can't tell them apart, but their vtables differ. The
+    // same error may also be seen through duplicated vtables, which only
+    // delays noticing a cycle, since going around it gets each error from
+    // the same `source()` every time.
+    core::ptr::eq(a, b)
+}
+
+impl<'a> Iterator for Chain<'a> {
     type Item = &'a ErrorRef;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.err?;
+        let mut next = self.err?;
+        if self.through_opaque {
+            // An `Opaque` prints the same as the error it hides, so it is
+            // only yielded as that error.
+            while let Some(op) = next.downcast_ref::<Opaque>() {
+                next = op.hidden();
+            }
+        }
+
+        if self.printed && !self.top && next.is::<Source>() {
+            self.err = None;
+            return None;
+        }
+
+        if let Some(saved) = self.saved {
+            if same(saved, next) {
+                self.err = None;
+                return None;
+            }
+        }
+
+        if self.steps == self.power {
+            self.saved = Some(next);
+            self.power = self.power.saturating_mul(2);
+            self.steps = 0;
+        }
+        self.steps += 1;
+
+        self.top = false;
         self.err = next.source();
         Some(next)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn depth() {
+        let err = ::new("a");
+        assert_eq!(super::depth(&err), 1);
+
+        let err = ::wrap("b", "a");
+        assert_eq!(super::depth(&err), 2);
+
+        // opaque hides the inner "b" and "a"
+        let err = ::wrap("d", ::wrap("c", ::opaque(::wrap("b", "a"))));
+        assert_eq!(super::depth(&err), 3);
+    }
+
+    #[test]
+    fn try_fold() {
+        use core::ops::ControlFlow;
+
+        let err = ::wrap("c", ::wrap("b", "a"));
+
+        let mut visited = 0;
+        let count = super::try_fold(&err, 0, |n, _| {
+            visited += 1;
+            ControlFlow::Continue(n + 1)
+        });
+        assert_eq!((count, visited), (3, 3));
+
+        let mut visited = 0;
+        let found = super::try_fold(&err, 0, |n, e| {
+            visited += 1;
+            if e.to_string() == "b" {
+                ControlFlow::Break(n)
+            } else {
+                ControlFlow::Continue(n + 1)
+            }
+        });
+        assert_eq!((found, visited), (1, 2));
+    }
+
+    #[test]
+    fn any_and_all() {
+        use alloc::string::ToString;
+
+        let err = ::wrap("c", ::wrap("b", "a"));
+        let is = |msg: &'static str| move |e: &::ErrorRef| e.to_string() == msg;
+
+        // top
+        assert!(super::any(&err, is("c")));
+        assert!(!super::all(&err, is("c")));
+        // source
+        assert!(super::any(&err, is("a")));
+        // nothing
+        assert!(!super::any(&err, is("z")));
+        assert!(super::all(&err, |e| e.to_string().len() == 1));
+        assert!(!super::all(&err, |e| e.source().is_some()));
+    }
+
+    #[test]
+    fn best() {
+        use alloc::string::ToString;
+        use std::io;
+
+        let rank = |e: &::ErrorRef| if e.is::<io::Error>() { 2 } else { 1 };
+
+        let err = ::wrap("c", ::wrap("b", io::Error::other("a")));
+        assert_eq!(super::best(&err, rank).to_string(), "a");
+
+        let err = ::wrap("c", io::Error::other(::wrap("b", "a")));
+        assert_eq!(super::best(&err, rank).to_string(), "b");
+
+        // ties go to the shallowest
+        let err = ::wrap("c", ::wrap("b", "a"));
+        assert_eq!(super::best(&err, rank).to_string(), "c");
+        assert_eq!(super::best(&err, |_| 0).to_string(), "c");
+    }
+
+    #[test]
+    fn type_counts() {
+        use core::fmt;
+        use core::num::ParseIntError;
+        use std::io;
+        use super::ErrorType;
+
+        let parse = "x".parse::<u8>().unwrap_err();
+        let err = io::Error::other(::wrap("b", io::Error::other(::wrap("a", parse))));
+        let types = [
+            ErrorType::of::<fmt::Error>(),
+            ErrorType::of::<ParseIntError>().named("ParseIntError"),
+            ErrorType::of::<io::Error>().named("io::Error"),
+        ];
+
+        let counts = super::type_counts(&err, &types).collect::<Vec<_>>();
+        assert_eq!(counts, [("ParseIntError", 1), ("io::Error", 2)]);
+
+        assert_eq!(super::type_counts(&err, &[]).count(), 0);
+        assert_eq!(ErrorType::of::<fmt::Error>().name(), "core::fmt::Error");
+    }
+
+    #[test]
+    fn all_of() {
+        use core::ptr;
+        use std::io;
+
+        let err = io::Error::other(::wrap("b", io::Error::other("a")));
+        let found = super::all_of::<io::Error>(&err).collect::<Vec<_>>();
+        assert_eq!(found.len(), 2);
+        assert!(ptr::eq(found[0], &err));
+        assert_eq!(found[1].to_string(), "a");
+
+        assert_eq!(super::all_of::<core::fmt::Error>(&err).count(), 0);
+    }
+
+    #[test]
+    fn root_or() {
+        use core::ptr;
+        use std::io;
+
+        let err = ::wrap("d", io::Error::other(::wrap("c", io::Error::other(::wrap("b", "a")))));
+        let found = super::root_or::<io::Error>(&err);
+        assert!(found.is::<io::Error>());
+        assert!(ptr::addr_eq(found, super::find_last::<io::Error>(&err).unwrap()));
+        assert_eq!(found.to_string(), "b");
+
+        let err = ::wrap("c", ::wrap("b", "a"));
+        assert_eq!(super::root_or::<io::Error>(&err).to_string(), "a");
+    }
+
+    #[test]
+    fn find_last() {
+        use core::ptr;
+        use std::io;
+
+        let err = ::wrap("c", io::Error::other(::wrap("b", io::Error::other("a"))));
+
+        let first = ::find::<io::Error>(&err).unwrap();
+        let last = super::find_last::<io::Error>(&err).unwrap();
+        assert!(!ptr::eq(first, last));
+        assert_eq!(last.to_string(), "a");
+        assert_eq!(super::position::<io::Error>(&err), Some(1));
+
+        let err = ::wrap("b", "a");
+        assert!(super::find_last::<io::Error>(&err).is_none());
+    }
+
+    // Every common shape of boxed error can be inspected by dereferencing.
+    #[test]
+    fn boxed_shapes() {
+        use std::error::Error;
+        use std::io;
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        fn check(err: &::ErrorRef) {
+            assert!(::is::<io::Error>(err));
+            assert_eq!(::find::<io::Error>(err).unwrap().to_string(), "a");
+            assert_eq!(super::chain(err).count(), 2);
+        }
+
+        fn check_generic<E: super::AsErrorRef + ?Sized>(err: &E) {
+            assert_eq!(super::chain(err).count(), 2);
+            check(err.as_error_ref());
+        }
+
+        let make = || ::wrap("b", io::Error::other("a"));
+
+        let err: Box<dyn Error> = Box::new(make());
+        check(&*err);
+        check_generic(&*err);
+
+        let err: Box<dyn Error + Send> = Box::new(make());
+        check(&*err);
+        check_generic(&*err);
+
+        let err: Box<dyn Error + Send + Sync> = Box::new(make());
+        check(&*err);
+        check_generic(&*err);
+
+        let err: Rc<dyn Error> = Rc::new(make());
+        check(&*err);
+        check_generic(&*err);
+
+        let err: Arc<dyn Error + Send + Sync> = Arc::new(make());
+        check(&*err);
+        check_generic(&*err);
+
+        let err = ::Error::from(make());
+        check(&*err);
+        check_generic(&*err);
+
+        let err = make();
+        check(&err);
+        check_generic(&err);
+    }
+
+    #[test]
+    fn within() {
+        use std::io;
+
+        let err = io::Error::other("a");
+        assert!(super::is_within::<io::Error>(&err, 1));
+        assert!(!super::is_within::<io::Error>(&err, 0));
+
+        let err = ::wrap("c", ::wrap("b", io::Error::other("a")));
+        assert!(super::is_within::<io::Error>(&err, 3));
+        assert!(super::is_within::<io::Error>(&err, 10));
+        assert_eq!(super::find_within::<io::Error>(&err, 3).unwrap().to_string(), "a");
+        assert!(!super::is_within::<io::Error>(&err, 2));
+        assert!(super::find_within::<io::Error>(&err, 2).is_none());
+    }
+
+    #[test]
+    fn position() {
+        use std::io;
+
+        let err = io::Error::other("a");
+        assert_eq!(super::position::<io::Error>(&err), Some(0));
+
+        let err = ::wrap("c", ::wrap("b", io::Error::other("a")));
+        assert_eq!(super::position::<io::Error>(&err), Some(2));
+        assert!(::find::<io::Error>(&err).is_some());
+
+        let err = ::wrap("b", "a");
+        assert_eq!(super::position::<io::Error>(&err), None);
+        assert!(::find::<io::Error>(&err).is_none());
+    }
+
+    #[test]
+    fn chain_rev() {
+        fn ptrs<'a>(iter: impl Iterator<Item = &'a ::ErrorRef>) -> Vec<*const ()> {
+            iter.map(|e| e as *const ::ErrorRef as *const ()).collect()
+        }
+
+        let err = ::new("a");
+        assert_eq!(ptrs(super::chain_rev(&err)), ptrs(super::chain(&err)));
+
+        let err = ::wrap("c", ::wrap("b", "a"));
+        let mut expected = ptrs(super::chain(&err));
+        expected.reverse();
+        assert_eq!(ptrs(super::chain_rev(&err)), expected);
+
+        let msgs = super::chain_rev(&err).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn pairs() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+        let items = super::pairs(&err)
+            .map(|(parent, child)| (parent.to_string(), child.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                ("c".to_string(), "b".to_string()),
+                ("b".to_string(), "a".to_string()),
+            ],
+        );
+
+        assert_eq!(super::pairs(&::new("a")).count(), 0);
+    }
+
+    #[test]
+    fn chain_send() {
+        use core::error::Error;
+        use core::fmt;
+        use std::io;
+        use SendErrorRef;
+
+        fn assert_send_sync<T: Send + Sync + ?Sized>(_: &T) {}
+
+        let err = ::wrap("c", ::wrap("b", "a"));
+        for e in super::chain_send(&err) {
+            assert_send_sync(e);
+        }
+        let messages = std::thread::scope(|s| {
+            s.spawn(|| super::chain_send(&err).map(|e| e.to_string()).collect::<Vec<_>>())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(messages, ["c", "b", "a"]);
+
+        // boxed and with fields too
+        let err: ::BoxError = ::wrap_boxed("c", ::wrap("b", "a"));
+        assert_eq!(super::chain_send(&*err).count(), 3);
+        let err = ::with_field(::wrap("b", "a"), "k", 1);
+        assert_eq!(super::chain_send(&*err).count(), 2);
+
+        // stops at the first foreign error
+        let err = ::wrap("c", io::Error::other(::wrap("b", "a")));
+        assert_eq!(super::chain(&err).count(), 3);
+        let errs = super::chain_send(&err).collect::<Vec<&SendErrorRef>>();
+        assert_eq!(errs.len(), 2);
+        assert!(errs[1].is::<io::Error>());
+
+        // as does an opaque
+        let err = ::wrap("c", ::opaque(::wrap("b", "a")));
+        assert_eq!(super::chain_send(&err).count(), 2);
+
+        // even one describing itself the same as an error of this crate
+        #[derive(Debug)]
+        struct Forward(::BoxError);
+
+        impl fmt::Display for Forward {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl Error for Forward {
+            #[allow(deprecated)]
+            fn description(&self) -> &str {
+                self.0.description()
+            }
+
+            fn source(&self) -> Option<&::ErrorRef> {
+                self.0.source()
+            }
+        }
+
+        let err = ::wrap("c", Forward(::wrap_boxed("b", "a")));
+        assert_eq!(super::chain(&err).count(), 3);
+        let errs = super::chain_send(&err).collect::<Vec<&SendErrorRef>>();
+        assert_eq!(errs.len(), 2);
+        assert!(errs[1].is::<Forward>());
+    }
+
+    #[test]
+    fn indexed() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+        let items = super::indexed(&err)
+            .map(|(i, is_root, e)| (i, is_root, e.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            [
+                (0, false, "c".to_string()),
+                (1, false, "b".to_string()),
+                (2, true, "a".to_string()),
+            ],
+        );
+
+        let err = ::new("a");
+        let mut iter = super::indexed(&err);
+        assert!(matches!(iter.next(), Some((0, true, _))));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn chain_clone() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+
+        let mut iter = super::chain(&err);
+        assert_eq!(iter.next().unwrap().to_string(), "c");
+
+        let fork = iter.clone();
+        let msgs = iter.map(|e| e.to_string()).collect::<Vec<_>>();
+        let fork_msgs = fork.map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["b", "a"]);
+        assert_eq!(fork_msgs, ["b", "a"]);
+    }
+
+    #[derive(Debug)]
+    struct Cycle(&'static str, &'static Cycle);
+
+    impl ::core::fmt::Display for Cycle {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl ::core::error::Error for Cycle {
+        fn source(&self) -> Option<&::ErrorRef> {
+            Some(self.1)
+        }
+    }
+
+    static SELF: Cycle = Cycle("self", &SELF);
+    static PING: Cycle = Cycle("ping", &PONG);
+    static PONG: Cycle = Cycle("pong", &PING);
+
+    #[test]
+    fn chain_stops_on_cycle() {
+        use core::error::Error;
+
+        // Start from what `source()` gives, so every error of the cycle is
+        // seen through the same vtable, and found again as soon as it can be.
+        let self_ = SELF.source().unwrap();
+        let msgs = super::chain(self_).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["self"]);
+        assert_eq!(super::root(self_).to_string(), "self");
+
+        let ping = PONG.source().unwrap();
+        let msgs = super::chain(ping).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["ping", "pong"]);
+
+        // a tail leading into a cycle
+        let err = ::wrap("b", ::wrap("a", &PING));
+        let msgs = super::chain(&err).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["b", "a", "ping", "pong", "ping", "pong", "ping", "pong"]);
+        assert!(!super::is::<::Opaque>(&err));
+        assert_eq!(format!("{:+.3}", err), "b: a: ping: pong");
+        // the sources are walked on their own, so it is found after another
+        // number of steps
+        assert_eq!(format!("{:+}", err), "b: a: ping: pong: ping");
+    }
+
+    #[test]
+    fn chain_source_at_same_address() {
+        use alloc::boxed::Box;
+        use core::fmt;
+
+        // The source is the first field, so shares the address of `Outer`.
+        #[derive(Debug)]
+        struct Outer(Inner);
+
+        #[derive(Debug)]
+        struct Inner;
+
+        impl fmt::Display for Outer {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("outer")
+            }
+        }
+
+        impl fmt::Display for Inner {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("inner")
+            }
+        }
+
+        impl ::core::error::Error for Outer {
+            fn source(&self) -> Option<&::ErrorRef> {
+                Some(&self.0)
+            }
+        }
+
+        impl ::core::error::Error for Inner {}
+
+        let err = Box::new(Outer(Inner));
+        let msgs = super::chain(&*err).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["outer", "inner"]);
+        assert!(super::is::<Inner>(&*err));
+    }
+
+    #[test]
+    fn chain_nested_at_same_address() {
+        use core::fmt;
+
+        // Each source is the only field of its parent, so all three share
+        // one address.
+        #[derive(Debug)]
+        struct A {
+            b: B,
+        }
+
+        #[derive(Debug)]
+        struct B {
+            c: C,
+        }
+
+        #[derive(Debug)]
+        struct C;
+
+        impl fmt::Display for A {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a")
+            }
+        }
+
+        impl fmt::Display for B {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("b")
+            }
+        }
+
+        impl fmt::Display for C {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("c")
+            }
+        }
+
+        impl ::core::error::Error for A {
+            fn source(&self) -> Option<&::ErrorRef> {
+                Some(&self.b)
+            }
+        }
+
+        impl ::core::error::Error for B {
+            fn source(&self) -> Option<&::ErrorRef> {
+                Some(&self.c)
+            }
+        }
+
+        impl ::core::error::Error for C {}
+
+        let err = A { b: B { c: C } };
+        let msgs = super::chain(&err).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["a", "b", "c"]);
+        assert!(super::is::<C>(&err));
+        assert!(super::find::<C>(&err).is_some());
+        assert_eq!(super::depth(&err), 3);
+        assert_eq!(super::root(&err).to_string(), "c");
+    }
+
+    #[test]
+    fn chain_generic() {
+        use std::io;
+
+        fn count<E: ::core::error::Error + 'static>(err: &E) -> usize {
+            super::chain(err).count()
+        }
+
+        fn messages<E: super::AsErrorRef + ?Sized>(err: &E) -> Vec<String> {
+            super::chain(err).map(|e| e.to_string()).collect()
+        }
+
+        let err = io::Error::other("a");
+        assert_eq!(count(&err), 1);
+        assert_eq!(messages(&err), ["a"]);
+
+        let err = ::wrap_boxed("b", err);
+        assert_eq!(messages(&*err), ["b", "a"]);
+
+        let err: &::ErrorRef = &*err;
+        assert_eq!(messages(err), ["b", "a"]);
+    }
+
+    #[test]
+    fn contains_message() {
+        let err = ::wrap("ship exploded", ::wrap("generator failed", "cat hair"));
+        assert!(super::contains_message(&err, "exploded"));
+        assert!(super::contains_message(&err, "hair"));
+        assert!(!super::contains_message(&err, "dog"));
+        // each message is searched on its own
+        assert!(!super::contains_message(&err, "exploded: generator"));
+    }
+
+    #[test]
+    fn fingerprint() {
+        let make = |top: &'static str| ::wrap(top, ::wrap("b", "a"));
+
+        let (x, y) = (make("c"), make("c"));
+        assert_eq!(super::fingerprint(&x), super::fingerprint(&y));
+        assert_ne!(super::fingerprint(&x), super::fingerprint(&make("d")));
+        assert_ne!(super::fingerprint(&x), super::fingerprint(&::wrap("c", "b")));
+        // messages don't run together
+        assert_ne!(
+            super::fingerprint_messages(&::wrap("ab", "c")),
+            super::fingerprint_messages(&::wrap("a", "bc")),
+        );
+
+        // the same messages, created on another line
+        let z = ::wrap("c", ::wrap("b", "a"));
+        assert_ne!(super::fingerprint(&x), super::fingerprint(&z));
+        assert_eq!(super::fingerprint_messages(&x), super::fingerprint_messages(&z));
+
+        // the hidden chain counts too
+        assert_ne!(
+            super::fingerprint(&::opaque(::wrap("b", "a"))),
+            super::fingerprint(&::opaque(::wrap("b", "z"))),
+        );
+        // but only once
+        assert_eq!(
+            super::fingerprint_messages(&::wrap("c", ::opaque(::wrap("b", "a")))),
+            super::fingerprint_messages(&x),
+        );
+
+        // stable across platforms and versions
+        assert_eq!(super::fingerprint_messages(&::new("a")), 0x089b_c907_b544_c769);
+    }
+
+    #[test]
+    fn join() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+        assert_eq!(super::join(&err, ": "), "c: b: a");
+        assert_eq!(super::join(&err, " <-- caused by -- "), "c <-- caused by -- b <-- caused by -- a");
+        assert_eq!(super::join(&err, ""), "cba");
+
+        assert_eq!(super::join(&::new("a"), " -> "), "a");
+    }
+
+    #[test]
+    fn messages() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+        let msgs = super::messages(&err).collect::<Vec<_>>();
+        assert_eq!(msgs, ["c", "b", "a"]);
+
+        // stops at the opaque barrier
+        let err = ::wrap("d", ::opaque(::wrap("c", ::wrap("b", "a"))));
+        let msgs = super::messages(&err).collect::<Vec<_>>();
+        assert_eq!(msgs, ["d", "c"]);
+    }
+
+    #[test]
+    fn chain_through_opaque() {
+        let err = ::wrap("d", ::opaque(::wrap("c", ::opaque(::wrap("b", "a")))));
+
+        let msgs = super::chain(&err).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(msgs, ["d", "c"]);
+
+        let msgs = super::chain_through_opaque(&err)
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, ["d", "c", "b", "a"]);
+
+        // even at the top
+        let err = ::opaque(::opaque(::wrap("b", "a")));
+        let msgs = super::chain_through_opaque(&err)
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, ["b", "a"]);
+        assert!(super::chain_through_opaque(&err).all(|e| !e.is::<::Opaque>()));
+    }
+
+    #[test]
+    fn find_map() {
+        let err = ::wrap("c", ::wrap("b", "a"));
+
+        let mut seen = Vec::new();
+        let found = super::find_map(&err, |e| {
+            let msg = e.to_string();
+            seen.push(msg.clone());
+            if msg != "c" {
+                Some(msg)
+            } else {
+                None
+            }
+        });
+
+        // returns the first match, and stops iterating once found
+        assert_eq!(found.as_ref().map(|s| &s[..]), Some("b"));
+        assert_eq!(seen, ["c", "b"]);
+
+        let found = super::find_map(&err, |_| None::<()>);
+        assert!(found.is_none());
+    }
+}
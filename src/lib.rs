@@ -1,5 +1,6 @@
 #![deny(missing_docs)]
 #![cfg_attr(test, deny(warnings))]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 //! `std::error::Error` extensions
 //!
@@ -13,9 +14,38 @@
 //!   [`errors::iter`](iter) iterators to find the error you're looking for.
 //! - **Error formatting**: The error values created with this crate provide
 //!   simple yet powerful control over the formatting of errors and their
-//!   source chains, and the [`errors::fmt`](fmt) adapter allows
+//!   source chains, and the [`errors::fmt`](fmt()) adapter allows
 //!   foreign error values to follow along.
 //!
+//! # Crate Features
+//!
+//! - **std** *(enabled by default)*: Enables [`errors::Main`](Main). Without
+//!   it, the crate only depends on `core` and `alloc`, using
+//!   `core::error::Error` for all the creation, inspection, and formatting
+//!   utilities.
+//! - **backtrace**: Captures a `std::backtrace::Backtrace` whenever an error
+//!   is created with this crate, which is included in the alternate (`{:#}`)
+//!   format and can be retrieved with `errors::backtrace`.
+//!   Implies **std**.
+//! - **time**: Records the `std::time::SystemTime` whenever an error is
+//!   created with this crate, which is included in the alternate (`{:#}`)
+//!   format and can be retrieved with `errors::created`. Implies **std**.
+//! - **serde**: Enables `errors::serde` to serialize an error and
+//!   its source chain.
+//! - **json**: Lets `errors::Main` print as JSON when the `ERRORS_FORMAT`
+//!   environment variable is `json`. Implies **std**.
+//! - **color**: Adds `errors::fmt::colored` to print an error with ANSI
+//!   colors in a terminal. Implies **std**.
+//! - **tracing**: Records the current `tracing::Span` whenever an error is
+//!   created with this crate, which can be retrieved with `errors::span`, and
+//!   adds `errors::emit` to log an error as a `tracing` event. Implies
+//!   **std**.
+//! - **log**: Adds `errors::log_error` to log an error with the `log`
+//!   crate, and `errors::Main::logged` to do so before exiting. Implies
+//!   **std**.
+//! - **tower**: Enables `errors::tower`, with a `Layer` that wraps the errors
+//!   of a `tower` service with context from the request. Implies **std**.
+//!
 //! # Creating Errors
 //!
 //! When an error condition has nothing special about besides a message, you
@@ -121,12 +151,15 @@
 //! # }
 //! ```
 //!
+//! When every error should be retried, [`errors::retry`](retry) does this
+//! for you.
+//!
 //! # Formatting Errors
 //!
 //! This crate defines a way for a user to specify how to easily format an
 //! error along with its source chain. All the error values created with this
 //! crate follow this spec, and any other errors can be adapted with the handy
-//! [`errors::fmt`](fmt) adapter.
+//! [`errors::fmt`](fmt()) adapter.
 //!
 //! ### Output options:
 //!
@@ -163,16 +196,20 @@
 //! - **With trace/frame (`{:#}`)**: Prints the message and stack trace/frame
 //!   - *Example*: `println!("top trace = {:#}", err)` outputs `top trace = ship exploded\n    at ship.rs:89`.
 //! - **Message chain with trace/frame (`{:+#}`)**: Prints the message and stack trace/frame, and message and trace for each source, joined by `\nCaused by:`.
+//!   - *Width*: `{:+#4}` indents each further source by 4 more spaces.
 //!
 //!
 //! ## `errors::Main`
 //!
+//! *Requires the `std` feature.*
+//!
 //! Newer versions of Rust allow returning a `Result` from the `main` function
 //! and it will be formatted and printed to the user. Using the
 //! [`errors::Main`](Main) type, you can easily convert any application errors
 //! such that the full source chain will be printed in a useful format.
 //!
 //! ```
+//! # #[cfg(feature = "std")]
 //! # mod not_main {
 //! # use std::fmt;
 //! #
@@ -206,16 +243,147 @@
 //! # }
 //! ```
 
-use std::error::Error;
+#[cfg(feature = "std")]
+extern crate core;
+extern crate alloc;
+// The tests use `std`, without the library itself needing it.
+#[cfg(all(test, not(feature = "std")))]
+#[macro_use]
+extern crate std;
+#[cfg(feature = "serde")]
+extern crate serde as serde_crate;
+#[cfg(any(feature = "json", all(test, feature = "serde")))]
+extern crate serde_json;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(feature = "tower")]
+extern crate tower_layer;
+#[cfg(feature = "tower")]
+extern crate tower_service;
+
+use alloc::boxed::Box;
+
+/// A boxed error, as accepted and returned by the functions of this crate.
+///
+/// # Example
+///
+/// ```
+/// fn load() -> Result<(), errors::BoxError> {
+///     Err(errors::wrap_boxed("config not found", "ship.toml"))
+/// }
+///
+/// assert!(load().is_err());
+/// ```
+pub type BoxError = Box<dyn core::error::Error + Send + Sync>;
 
-type BoxError = Box<dyn Error + Send + Sync>;
-type ErrorRef = dyn Error + 'static;
+/// A reference to any error, as accepted by the functions of this crate.
+///
+/// # Example
+///
+/// ```
+/// fn handle(err: &errors::ErrorRef) -> usize {
+///     errors::iter::depth(err)
+/// }
+///
+/// let err: errors::BoxError = errors::wrap_boxed("b", "a");
+/// assert_eq!(handle(&*err), 2);
+/// ```
+pub type ErrorRef = dyn core::error::Error + 'static;
 
-mod fmt;
+/// A reference to any error that is also `Send + Sync`, as yielded by
+/// [`errors::iter::chain_send`](iter::chain_send).
+pub type SendErrorRef = dyn core::error::Error + Send + Sync + 'static;
+
+#[macro_use]
+mod macros;
+
+mod error;
+mod ext;
+pub mod fmt;
 pub mod iter;
 mod new;
+mod retry;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "tower")]
+pub mod tower;
+
+pub use self::error::{Error, Source, StaticError};
+pub use self::ext::{ErrorExt, ResultExt};
+pub use self::fmt::{eq, fmt, render_into, Report};
+#[cfg(feature = "std")]
+pub use self::fmt::{write_chain, Main};
+pub use self::iter::{find, fingerprint, fingerprint_messages, is};
+#[cfg(feature = "backtrace")]
+pub use self::new::backtrace;
+#[cfg(feature = "time")]
+pub use self::new::created;
+#[cfg(feature = "std")]
+pub use self::new::{into_io, into_io_with_kind};
+#[cfg(feature = "tracing")]
+pub use self::new::{emit, span};
+#[cfg(feature = "log")]
+pub use self::new::log_error;
+pub use self::new::{
+    aggregate,
+    attach,
+    categorized,
+    clone_chain,
+    downcast,
+    fields,
+    from_messages,
+    from_parts,
+    is_opaque,
+    kind_of,
+    lazy,
+    map_message,
+    msg,
+    new,
+    new_boxed,
+    new_static,
+    new_typed,
+    opaque,
+    opaque_if,
+    opaque_keep_root,
+    opaque_tag,
+    opaque_tagged,
+    opaque_typed,
+    split_opaque,
+    type_name,
+    wrap,
+    wrap_all,
+    wrap_args,
+    wrap_at,
+    wrap_boxed,
+    wrap_opt,
+    wrap_ref_err,
+    wrap_typed,
+    wrap_with,
+    with_field,
+    Opaque,
+};
+pub use self::retry::retry;
+#[doc(hidden)]
+pub use self::new::{__format_error, __formatted_error, __lazy_message};
+
+
+#[cfg(test)]
+mod tests {
+    // Only uses `core` and `alloc`, to smoke out anything that would
+    // accidentally require `std` when built with `default-features = false`.
+    #[test]
+    fn no_std_smoke() {
+        use alloc::format;
+        use alloc::string::ToString;
+        use core::error::Error;
 
-pub use self::fmt::{fmt, Main};
-pub use self::iter::{find, is};
-pub use self::new::{new, opaque, wrap};
+        let err = ::wrap("ship exploded", ::new("cat hair in generator"));
 
+        assert_eq!(err.to_string(), "ship exploded");
+        assert_eq!(format!("{:+}", err), "ship exploded: cat hair in generator");
+        assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
+        assert_eq!(::iter::root(&err).to_string(), "cat hair in generator");
+    }
+}
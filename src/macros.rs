@@ -0,0 +1,295 @@
+//! Macros to create errors with format string messages.
+
+/// Create an error value with a format string message.
+///
+/// The message is only formatted when the error is, so no `String` is built
+/// for an error that is never displayed. Otherwise, this is the same as
+/// [`errors::new`](::new()).
+///
+/// The arguments are evaluated once, and moved into the error, so they must
+/// be `Send + Sync + 'static`.
+///
+/// # Example
+///
+/// ```
+/// let path = "ship.toml";
+/// let err = errors::new!("no such file: {}", path);
+///
+/// assert_eq!(err.to_string(), "no such file: ship.toml");
+/// ```
+#[macro_export]
+macro_rules! new {
+    ($fmt:literal $(,)?) => {
+        $crate::new($fmt)
+    };
+    ($fmt:literal, $($args:tt)+) => {
+        $crate::__format_error($crate::__format_message!(@bind ($fmt) () $($args)+), None)
+    };
+}
+
+/// Wrap an error with a format string message.
+///
+/// The cause comes first, followed by `=>` and then the format string and its
+/// arguments, so it reads apart from the message. The message is only
+/// formatted when the error is, so no `String` is built for an error that is
+/// never displayed. Otherwise, this is the same as [`errors::wrap`](::wrap()).
+///
+/// The arguments are evaluated once, and moved into the error, so they must
+/// be `Send + Sync + 'static`.
+///
+/// # Example
+///
+/// ```
+/// let attempts = 3;
+/// let err = errors::wrap!("timed out" => "gave up after {} attempts", attempts);
+///
+/// assert_eq!(format!("{:+}", err), "gave up after 3 attempts: timed out");
+/// ```
+#[macro_export]
+macro_rules! wrap {
+    ($cause:expr => $fmt:literal $(,)?) => {
+        $crate::wrap($fmt, $cause)
+    };
+    ($cause:expr => $fmt:literal, $($args:tt)+) => {
+        $crate::__format_error(
+            $crate::__format_message!(@bind ($fmt) () $($args)+),
+            Some($crate::BoxError::from($cause)),
+        )
+    };
+}
+
+/// Create a closure that wraps an error with a format string message, for
+/// `Result::map_err`.
+///
+/// Nothing is evaluated or formatted until the closure is called with an
+/// error, so it costs nothing on the `Ok` path. The message is then formatted
+/// right away into an owned string, so unlike [`errors::new!`](new!) and
+/// [`errors::wrap!`](wrap!), the arguments may borrow.
+///
+/// # Example
+///
+/// ```
+/// let path = String::from("ship.toml");
+/// let res: Result<(), _> = Err("no such file");
+///
+/// let err = res.map_err(errors::format_err!("failed to load {}", &path)).unwrap_err();
+///
+/// assert_eq!(format!("{:+}", err), "failed to load ship.toml: no such file");
+/// ```
+#[macro_export]
+macro_rules! format_err {
+    ($fmt:literal $(, $($args:tt)*)?) => {
+        |cause| $crate::__formatted_error(format_args!($fmt $(, $($args)*)?), Some($crate::BoxError::from(cause)))
+    };
+}
+
+/// Return early with an error if a condition is false.
+///
+/// The error is made as with [`errors::new!`](new!), but the message is only
+/// formatted if the condition is false. It is then converted with `.into()`,
+/// the same as by the `?` operator, so the function can return a
+/// [`BoxError`](::BoxError), an [`errors::Main`](::Main), or any other error
+/// type it converts into.
+///
+/// # Example
+///
+/// ```
+/// fn launch(fuel: u32) -> Result<(), errors::BoxError> {
+///     errors::ensure!(fuel >= 10, "not enough fuel: {}", fuel);
+///     Ok(())
+/// }
+///
+/// assert!(launch(10).is_ok());
+/// assert_eq!(launch(3).unwrap_err().to_string(), "not enough fuel: 3");
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $fmt:literal $(, $($args:tt)*)?) => {
+        if !$cond {
+            $crate::bail!($fmt $(, $($args)*)?);
+        }
+    };
+}
+
+/// Return early with an error.
+///
+/// This is the same as [`errors::ensure!`](ensure!) with a condition that is
+/// always false.
+///
+/// # Example
+///
+/// ```
+/// fn launch(destination: &str) -> Result<(), errors::BoxError> {
+///     if destination == "sun" {
+///         errors::bail!("too hot: {}", destination);
+///     }
+///     Ok(())
+/// }
+///
+/// assert_eq!(launch("sun").unwrap_err().to_string(), "too hot: sun");
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($fmt:literal $(, $($args:tt)*)?) => {
+        return Err($crate::__formatted_error(format_args!($fmt $(, $($args)*)?), None).into())
+    };
+}
+
+// Binds each argument to its own (hygienic) `arg`, so that they are only
+// evaluated once, before moving them all into the message.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_message {
+    (@bind ($fmt:literal) ($($bound:tt)*) $(,)?) => {
+        $crate::__lazy_message(move |f| write!(f, $fmt, $($bound)*))
+    };
+    (@bind ($fmt:literal) ($($bound:tt)*) $name:ident = $val:expr $(, $($rest:tt)*)?) => {{
+        let arg = $val;
+        $crate::__format_message!(@bind ($fmt) ($($bound)* $name = arg,) $($($rest)*)?)
+    }};
+    (@bind ($fmt:literal) ($($bound:tt)*) $val:expr $(, $($rest:tt)*)?) => {{
+        let arg = $val;
+        $crate::__format_message!(@bind ($fmt) ($($bound)* arg,) $($($rest)*)?)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use core::cell::Cell;
+    use core::error::Error;
+
+    #[test]
+    fn new_positional_args() {
+        let err = new!("no such file: {}, {}", "ship.toml", 3);
+        assert_eq!(err.to_string(), "no such file: ship.toml, 3");
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn new_named_args() {
+        let name = String::from("ship.toml");
+        let err = new!("{name} at line {line}, {}", "oops", line = 3);
+        assert_eq!(err.to_string(), "ship.toml at line 3, oops");
+    }
+
+    #[test]
+    fn new_without_args() {
+        let err = new!("ship exploded");
+        assert_eq!(format!("{:+}", err), "ship exploded");
+    }
+
+    #[test]
+    fn new_matches_function() {
+        let (a, b) = (new!("{}", 5), ::new("5"));
+        assert_eq!(a.to_string(), b.to_string());
+        assert_eq!(format!("{:+}", ::wrap("c", a)), format!("{:+}", ::wrap("c", b)));
+    }
+
+    #[test]
+    fn location_is_call_site() {
+        let (err, line) = (new!("ship {}", "exploded"), line!());
+        let loc = format!("\n    at {}:{}", file!(), line);
+        assert!(format!("{:#}", err).contains(&loc));
+    }
+
+    #[test]
+    fn args_evaluated_once() {
+        let calls = Cell::new(0);
+        let next = || {
+            calls.set(calls.get() + 1);
+            calls.get()
+        };
+
+        let err = new!("call {}", next());
+        assert_eq!(err.to_string(), "call 1");
+        assert_eq!(err.to_string(), "call 1");
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn format_err_only_on_err() {
+        let calls = Cell::new(0);
+        let next = || {
+            calls.set(calls.get() + 1);
+            calls.get()
+        };
+
+        let res: Result<u8, &str> = Ok(5);
+        assert_eq!(res.map_err(format_err!("call {}", next())).unwrap(), 5);
+        assert_eq!(calls.get(), 0);
+
+        let res: Result<u8, &str> = Err("cat hair");
+        let err = res.map_err(format_err!("call {n}", n = next())).unwrap_err();
+        assert_eq!(calls.get(), 1);
+        assert_eq!(format!("{:+}", err), "call 1: cat hair");
+        assert_eq!(format!("{:-?}", err), "call 1");
+
+        let res: Result<u8, &str> = Err("cat hair");
+        let err = res.map_err(format_err!("ship exploded")).unwrap_err();
+        assert_eq!(format!("{:+}", err), "ship exploded: cat hair");
+    }
+
+    fn check(fuel: u32, destination: &str) -> Result<u32, ::BoxError> {
+        ensure!(fuel > 0, "no fuel");
+        ensure!(fuel >= 10, "not enough fuel: {}", fuel);
+        if destination == "sun" {
+            bail!("too hot: {destination}");
+        }
+        Ok(fuel)
+    }
+
+    #[test]
+    fn ensure_and_bail() {
+        assert_eq!(check(10, "moon").unwrap(), 10);
+        assert_eq!(check(0, "moon").unwrap_err().to_string(), "no fuel");
+
+        let err = check(3, "moon").unwrap_err();
+        assert_eq!(err.to_string(), "not enough fuel: 3");
+        assert!(err.source().is_none());
+
+        assert_eq!(check(10, "sun").unwrap_err().to_string(), "too hot: sun");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ensure_and_bail_into_main() {
+        fn launch(fuel: u32) -> Result<u32, ::Main> {
+            ensure!(fuel >= 10, "not enough fuel: {}", fuel);
+            if fuel > 100 {
+                bail!("too much fuel");
+            }
+            Ok(fuel)
+        }
+
+        assert_eq!(launch(10).unwrap(), 10);
+        assert!(format!("{:?}", launch(3).unwrap_err()).starts_with("not enough fuel: 3\n"));
+        assert!(format!("{:?}", launch(200).unwrap_err()).starts_with("too much fuel\n"));
+    }
+
+    #[test]
+    fn type_name_is_format_string() {
+        let args = core::any::type_name::<core::fmt::Arguments>();
+        assert_eq!(::type_name(&new!("ship {}", 3)), args);
+        assert_eq!(::type_name(&wrap!("cat hair" => "ship {}", 3)), args);
+
+        let string = core::any::type_name::<String>();
+        let res: Result<u8, &str> = Err("cat hair");
+        assert_eq!(::type_name(&res.map_err(format_err!("ship {}", 3)).unwrap_err()), string);
+        assert_eq!(::type_name(&*check(3, "moon").unwrap_err()), string);
+    }
+
+    #[test]
+    fn wrap_args() {
+        let err = wrap!("cat hair" => "ship {} exploded", 3);
+        assert_eq!(format!("{:+}", err), "ship 3 exploded: cat hair");
+        assert_eq!(err.source().unwrap().to_string(), "cat hair");
+
+        let err = wrap!(err => "launch {n}", n = "failed");
+        assert_eq!(format!("{:+}", err), "launch failed: ship 3 exploded: cat hair");
+
+        let err = wrap!("cat hair" => "ship exploded");
+        assert_eq!(format!("{:+}", err), "ship exploded: cat hair");
+    }
+}
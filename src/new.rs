@@ -1,5 +1,17 @@
-use std::fmt;
-use super::{BoxError, Error, ErrorRef};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{self, Write};
+use core::panic::Location;
+#[cfg(feature = "backtrace")]
+use std::backtrace::{Backtrace, BacktraceStatus};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "time")]
+use std::time::{SystemTime, UNIX_EPOCH};
+use core::error::Error;
+use super::{BoxError, ErrorRef, SendErrorRef};
 
 /// Simple way to create an error value.
 ///
@@ -10,14 +22,82 @@ use super::{BoxError, Error, ErrorRef};
 ///
 /// assert_eq!(err.to_string(), "sound the alarm");
 /// ```
+#[track_caller]
 pub fn new<D>(err: D) -> impl Error
 where
     D: fmt::Debug + fmt::Display + Send + Sync + 'static,
 {
-    Wrapper {
-        message: err,
-        cause: None,
-    }
+    Wrapper::new(err, None)
+}
+
+/// Create an error value with a static string message.
+///
+/// The same as [`errors::new`](new), except that the `Debug` output prints
+/// the message as is, like `Display`, instead of quoted and escaped like the
+/// `Debug` of a `&str`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::new_static("sound the alarm");
+///
+/// assert_eq!(format!("{:?}", err), "sound the alarm");
+/// assert_eq!(format!("{:?}", errors::new("sound the alarm")), "\"sound the alarm\"");
+/// ```
+#[track_caller]
+pub fn new_static(message: &'static str) -> impl Error {
+    Wrapper::named(Static(message), None, core::any::type_name::<&str>())
+}
+
+/// Create an error value whose message is rendered when it is formatted.
+///
+/// The closure is called every time the error is formatted, with either
+/// `Display` or `Debug`, and never if it isn't formatted at all. This is
+/// useful when building the message is expensive, but it does mean the
+/// closure may run many times, so it should write the same output each time.
+///
+/// # Example
+///
+/// ```
+/// let ids = vec![1, 2, 3];
+/// let err = errors::lazy(move |f| write!(f, "unknown ids: {:?}", ids));
+///
+/// assert_eq!(err.to_string(), "unknown ids: [1, 2, 3]");
+/// ```
+#[track_caller]
+pub fn lazy<F>(f: F) -> impl Error
+where
+    F: Fn(&mut fmt::Formatter) -> fmt::Result + Send + Sync + 'static,
+{
+    Wrapper::named(Lazy(f), None, core::any::type_name::<F>())
+}
+
+// Used by the `new!` and `wrap!` macros.
+#[doc(hidden)]
+pub fn __lazy_message<F>(f: F) -> impl fmt::Debug + fmt::Display + Send + Sync + 'static
+where
+    F: Fn(&mut fmt::Formatter) -> fmt::Result + Send + Sync + 'static,
+{
+    Lazy(f)
+}
+
+// Used by the `new!` and `wrap!` macros, with a message from
+// `__lazy_message`, which is reported as the format string it is.
+#[doc(hidden)]
+#[track_caller]
+pub fn __format_error<D>(message: D, cause: Option<BoxError>) -> impl Error
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+{
+    Wrapper::named(message, cause, core::any::type_name::<fmt::Arguments>())
+}
+
+// Used by the `format_err!`, `bail!`, and `ensure!` macros.
+#[doc(hidden)]
+#[track_caller]
+pub fn __formatted_error(args: fmt::Arguments, cause: Option<BoxError>) -> impl Error {
+    let message = Formatted(alloc::fmt::format(args).into_boxed_str());
+    Wrapper::named(message, cause, core::any::type_name::<String>())
 }
 
 /// Wrap an error with some additional message.
@@ -32,17 +112,400 @@ where
 /// assert_eq!(err.to_string(), "exploded");
 /// assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
 /// ```
+#[track_caller]
 pub fn wrap<D, E>(message: D, cause: E) -> impl Error
 where
     D: fmt::Debug + fmt::Display + Send + Sync + 'static,
     E: Into<BoxError>,
 {
-    Wrapper {
+    Wrapper::new(message, Some(cause.into()))
+}
+
+/// Wrap an error with some additional message, if there is one.
+///
+/// The same as [`errors::wrap`](wrap) if `cause` is `Some`, or
+/// [`errors::new`](new) if it is `None`.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+///
+/// let err = errors::wrap_opt("exploded", Some("cat hair in generator"));
+/// assert_eq!(err.source().unwrap().to_string(), "cat hair in generator");
+///
+/// let err = errors::wrap_opt("exploded", None::<&str>);
+/// assert!(err.source().is_none());
+/// ```
+#[track_caller]
+pub fn wrap_opt<D, E>(message: D, cause: Option<E>) -> impl Error
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    E: Into<BoxError>,
+{
+    Wrapper::new(message, cause.map(Into::into))
+}
+
+/// Wrap an error with a lazily constructed message.
+///
+/// The closure is only called when an error is actually being wrapped, so
+/// it is a good fit for `map_err` where the message may be costly to build.
+///
+/// ```
+/// use std::error::Error;
+///
+/// let path = "/etc/ship.conf";
+/// let res: Result<(), _> = Err("file not found");
+/// let err = res
+///     .map_err(|e| errors::wrap_with(|| format!("failed to open {}", path), e))
+///     .unwrap_err();
+///
+/// assert_eq!(err.to_string(), "failed to open /etc/ship.conf");
+/// assert_eq!(err.source().unwrap().to_string(), "file not found");
+/// ```
+#[track_caller]
+pub fn wrap_with<D, E, F>(f: F, cause: E) -> impl Error
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    E: Into<BoxError>,
+    F: FnOnce() -> D,
+{
+    Wrapper::new(f(), Some(cause.into()))
+}
+
+/// Wrap each error of an iterator of `Result`s with a message.
+///
+/// `msg` is called with the index of each `Err` in `iter`, to build the
+/// message to wrap it with. `Ok` items are passed through unchanged.
+///
+/// # Example
+///
+/// ```
+/// let results = vec![Ok(1), Err("not a number"), Ok(3), Err("too big")];
+///
+/// let wrapped = errors::wrap_all(|i| format!("item {}", i), results)
+///     .map(|res| res.map_err(|e| format!("{:+}", e)))
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(wrapped[0], Ok(1));
+/// assert_eq!(wrapped[1], Err("item 1: not a number".to_string()));
+/// assert_eq!(wrapped[3], Err("item 3: too big".to_string()));
+/// ```
+// Same as `errors::retry`, the error is as large as what `errors::wrap` returns.
+#[allow(clippy::result_large_err)]
+#[track_caller]
+pub fn wrap_all<I, T, E, D, F>(msg: F, iter: I) -> impl Iterator<Item = Result<T, impl Error>>
+where
+    I: IntoIterator<Item = Result<T, E>>,
+    E: Into<BoxError>,
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    F: Fn(usize) -> D,
+{
+    // Closures can't track the caller, so use the caller of `wrap_all`
+    let location = Frame::Location(Location::caller());
+    iter.into_iter()
+        .enumerate()
+        .map(move |(i, res)| res.map_err(|e| Wrapper::at(msg(i), Some(e.into()), location)))
+}
+
+/// Wrap an error with some additional message, and a custom frame.
+///
+/// Errors created by this crate print the source location they were created
+/// at in the alternate (`{:#}`) format. This prints `frame` there instead,
+/// such as the name of a logical task or span that is more useful than a
+/// file and line. Otherwise, this is the same as [`errors::wrap`](wrap).
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap_at("fetch failed", "timed out", "task fetch_user#42");
+///
+/// assert!(format!("{:#}", err).starts_with("fetch failed\n    at task fetch_user#42"));
+/// ```
+pub fn wrap_at<D, E>(message: D, cause: E, frame: &'static str) -> impl Error
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    E: Into<BoxError>,
+{
+    Wrapper::at(message, Some(cause.into()), Frame::Label(frame))
+}
+
+/// Wrap an error with a message from `format_args!`, without allocating.
+///
+/// The message is formatted each time the error is, straight from the
+/// arguments, so nothing is rendered into a `String`.
+///
+/// # Lifetimes
+///
+/// `fmt::Arguments` borrows its arguments, often from temporaries that only
+/// live until the end of the enclosing statement. So the returned error
+/// borrows them too: it can't be boxed, returned, or passed to anything
+/// needing an `Error + 'static`, such as [`errors::iter`](::iter). It is meant
+/// for errors that are formatted right where they are created, such as when
+/// logging. Use [`errors::wrap!`](::wrap!) for an error that must outlive the
+/// arguments.
+///
+/// # Example
+///
+/// ```
+/// let id = 7;
+///
+/// let line = format!(
+///     "{:+}",
+///     errors::wrap_args(format_args!("request #{} failed", id), "timed out"),
+/// );
+///
+/// assert_eq!(line, "request #7 failed: timed out");
+/// ```
+#[track_caller]
+pub fn wrap_args<'a, E>(args: fmt::Arguments<'a>, cause: E) -> impl Error + 'a
+where
+    E: Into<BoxError>,
+{
+    Wrapper::inline(
+        args,
+        Some(cause.into()),
+        Frame::Location(Location::caller()),
+        core::any::type_name::<fmt::Arguments>(),
+    )
+}
+
+/// Wrap an error with a message made from its own message.
+///
+/// `f` is given the top message of `err`, and returns the message of the new
+/// error. An error can't be changed in place, so `err` is kept as the `source`
+/// of the new error: the chain grows by one, and `{:+}` prints both the new
+/// and the original message.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+///
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+/// let err = errors::map_message(err, |msg| msg.to_uppercase());
+///
+/// assert_eq!(err.to_string(), "SHIP EXPLODED");
+/// assert_eq!(err.source().unwrap().to_string(), "ship exploded");
+/// assert_eq!(
+///     format!("{:+}", err),
+///     "SHIP EXPLODED: ship exploded: cat hair in generator"
+/// );
+/// ```
+#[track_caller]
+pub fn map_message<E, D, F>(err: E, f: F) -> impl Error
+where
+    E: Into<BoxError>,
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    F: FnOnce(String) -> D,
+{
+    let err = err.into();
+    let message = f(err.to_string());
+    Wrapper::new(message, Some(err))
+}
+
+/// Wrap a borrowed error with some additional message.
+///
+/// This is like [`errors::wrap`](wrap), but for when the cause can't be moved,
+/// such as the error of a `Result` that is only borrowed. The cause is the
+/// `source` of the new error, and the formatting rules apply the same.
+///
+/// # Lifetimes
+///
+/// The returned error borrows the cause, so it can't outlive it, nor be boxed
+/// or passed to anything needing an `Error + 'static`. It is meant for
+/// errors that are formatted while the cause is still around, such as when
+/// logging.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let res: Result<(), io::Error> = Err(io::Error::other("disk on fire"));
+///
+/// if let Err(ref cause) = res {
+///     let err = errors::wrap_ref_err("read failed", cause);
+///     assert_eq!(format!("{:+}", err), "read failed: disk on fire");
+/// }
+///
+/// // `res` still owns its error
+/// assert!(res.is_err());
+/// ```
+#[track_caller]
+pub fn wrap_ref_err<'a, D>(message: D, cause: &'a ErrorRef) -> impl Error + 'a
+where
+    D: fmt::Debug + fmt::Display + 'a,
+{
+    WrapperRef {
         message,
-        cause: Some(cause.into()),
+        cause: Some(cause),
+        location: Some(Frame::Location(Location::caller())),
+        #[cfg(feature = "backtrace")]
+        backtrace: None,
+        #[cfg(feature = "time")]
+        created: Some(SystemTime::now()),
+        fields: &[],
+        separator: ": ",
+        caused_by: CAUSED_BY,
+        message_frame: false,
     }
 }
 
+/// Attach an error underneath another, without changing the message.
+///
+/// This is the inverse of [`errors::wrap`](wrap): the new error displays the
+/// message of `err`, with `meta` as its source, followed by the sources of
+/// `err`. Both `err` and `meta` can be found with [`errors::is`](::is) and
+/// [`errors::find`](::find), as can the sources of `err`, but the sources of
+/// `meta` are left out of the chain.
+///
+/// # Example
+///
+/// ```
+/// #[derive(Debug)]
+/// struct RequestId(u64);
+///
+/// impl std::fmt::Display for RequestId {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "request #{}", self.0)
+///     }
+/// }
+///
+/// impl std::error::Error for RequestId {}
+///
+/// let err = errors::attach("connection reset", RequestId(7));
+///
+/// assert_eq!(err.to_string(), "connection reset");
+/// assert_eq!(errors::find::<RequestId>(&err).unwrap().0, 7);
+/// ```
+pub fn attach<E, M>(err: E, meta: M) -> impl Error
+where
+    E: Into<BoxError>,
+    M: Into<BoxError>,
+{
+    Attached(Attachment {
+        meta: meta.into(),
+        err: err.into(),
+    })
+}
+
+/// Create an error value with a message and a kind.
+///
+/// The kind can be any small `Copy` value, such as a fieldless enum, for
+/// quick categories without defining a whole error type. It is printed
+/// before the message, like `[Network] connection reset`, and can be found
+/// again with [`errors::kind_of`](kind_of), even through wrapping.
+///
+/// # Example
+///
+/// ```
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// enum Kind {
+///     Network,
+///     Config,
+/// }
+///
+/// let err = errors::categorized(Kind::Network, "connection reset");
+/// assert_eq!(err.to_string(), "[Network] connection reset");
+///
+/// let err = errors::wrap("request failed", err);
+/// assert_eq!(errors::kind_of::<Kind>(&err), Some(Kind::Network));
+/// ```
+#[track_caller]
+pub fn categorized<K, D>(kind: K, message: D) -> impl Error
+where
+    K: Copy + fmt::Debug + Send + Sync + 'static,
+    D: fmt::Display + Send + Sync + 'static,
+{
+    Wrapper::named(
+        Kinded {
+            kind,
+            message: Box::new(message),
+        },
+        None,
+        core::any::type_name::<K>(),
+    )
+}
+
+/// Get the kind of the first error in the source chain created with
+/// [`errors::categorized`](categorized) with a kind of type `K`.
+pub fn kind_of<K>(err: &ErrorRef) -> Option<K>
+where
+    K: Copy + fmt::Debug + Send + Sync + 'static,
+{
+    ::iter::find_map(err, |e| {
+        typed(e)
+            .downcast_ref::<Wrapper>()
+            .and_then(|w| (*w.message).as_any().downcast_ref::<Kinded<K>>())
+            .map(|kinded| kinded.kind)
+    })
+}
+
+/// Create a new boxed error value.
+///
+/// The same as [`errors::new`](new), but boxed, so that it can be stored
+/// alongside other errors without needing an `.into()`.
+///
+/// # Example
+///
+/// ```
+/// let errs = vec![
+///     errors::new_boxed("sound the alarm"),
+///     errors::new_boxed("abandon ship"),
+/// ];
+///
+/// assert_eq!(errs[1].to_string(), "abandon ship");
+/// ```
+#[track_caller]
+pub fn new_boxed<D>(message: D) -> BoxError
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+{
+    Box::new(Wrapper::new(message, None))
+}
+
+/// Create a new boxed error value from an owned message.
+///
+/// The same as [`errors::new_boxed`](new_boxed), but the `String` is stored
+/// as a `Box<str>`, dropping any extra capacity. Shrinking it may move the
+/// message into a new allocation, so this is for a `String` kept around
+/// with much more capacity than it needs.
+///
+/// # Example
+///
+/// ```
+/// let name = "Nostromo";
+/// let err: errors::BoxError = errors::msg(format!("ship {} exploded", name));
+///
+/// assert_eq!(err.to_string(), "ship Nostromo exploded");
+/// ```
+#[track_caller]
+pub fn msg(message: String) -> BoxError {
+    Box::new(Wrapper::new(message.into_boxed_str(), None))
+}
+
+/// Wrap an error with some additional message, returning it boxed.
+///
+/// The same as [`errors::wrap`](wrap), but boxed, so that it can be stored
+/// alongside other errors without needing an `.into()`.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap_boxed("exploded", "cat hair in generator");
+///
+/// assert_eq!(format!("{:+}", err), "exploded: cat hair in generator");
+/// ```
+#[track_caller]
+pub fn wrap_boxed<D, E>(message: D, cause: E) -> BoxError
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    E: Into<BoxError>,
+{
+    Box::new(Wrapper::new(message, Some(cause.into())))
+}
+
 /// Wrap a value as a new `Error`, while hiding its source chain.
 ///
 /// The value is used for formatting, but not exposed as the `source`.
@@ -73,307 +536,2805 @@ where
 /// // But is no longer programatically available.
 /// assert!(err.source().is_none());
 /// ```
+///
+/// Inspecting the chain, such as with [`errors::is`](::is) or
+/// [`errors::find`](::find), only finds the [`Opaque`] itself, never any of
+/// the types it hides. Only [`chain_through_opaque`](::iter::chain_through_opaque)
+/// looks inside.
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::opaque(errors::wrap("request failed", io::Error::other("timeout")));
+///
+/// assert!(errors::is::<errors::Opaque>(&err));
+/// assert!(!errors::is::<io::Error>(&err));
+/// assert!(errors::find::<io::Error>(&err).is_none());
+/// ```
 pub fn opaque<E>(err: E) -> impl Error
 where
     E: Into<BoxError>,
 {
-    Opaque(err.into())
+    Opaque {
+        inner: err.into(),
+        tag: None,
+        keep_root: false,
+    }
+}
+
+/// Create a new [`errors::Error`](::Error) with a message.
+///
+/// This is the same as [`errors::new`](new), but with a nameable return type.
+#[track_caller]
+pub fn new_typed<D>(message: D) -> ::Error
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+{
+    ::Error::from(new_boxed(message))
 }
 
-pub(crate) fn wrap_ref<'a>(err: &'a dyn Error) -> impl Error + 'a {
-    WrapperRef {
-        message: err,
-        cause: err.source(),
+/// Wrap an error as a new [`errors::Error`](::Error).
+///
+/// This is the same as [`errors::wrap`](wrap), but with a nameable return
+/// type.
+#[track_caller]
+pub fn wrap_typed<D, E>(message: D, cause: E) -> ::Error
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    E: Into<BoxError>,
+{
+    ::Error::from(wrap_boxed(message, cause))
+}
+
+/// Hide the source chain of an error, only if a predicate holds.
+///
+/// The predicate is called with the error, and if it returns `true`, this is
+/// the same as [`errors::opaque`](opaque). Otherwise, the error is returned
+/// unchanged.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap("request failed", io::Error::from(io::ErrorKind::TimedOut));
+/// let err = errors::opaque_if(err, errors::is::<io::Error>);
+///
+/// assert!(!errors::is::<io::Error>(&*err));
+/// ```
+pub fn opaque_if<E, F>(err: E, pred: F) -> BoxError
+where
+    E: Into<BoxError>,
+    F: FnOnce(&ErrorRef) -> bool,
+{
+    let err = err.into();
+    if pred(&*err) {
+        Box::new(Opaque {
+            inner: err,
+            tag: None,
+            keep_root: false,
+        })
+    } else {
+        err
     }
 }
 
-struct Wrapper<D> {
-    message: D,
-    cause: Option<BoxError>,
-}
+/// Hide the source chain of an error in a new [`errors::Error`](::Error).
+///
+/// This is the same as [`errors::opaque`](opaque), but with a nameable
+/// return type.
+pub fn opaque_typed<E>(err: E) -> ::Error
+where
+    E: Into<BoxError>,
+{
+    ::Error::from(Opaque {
+        inner: err.into(),
+        tag: None,
+        keep_root: false,
+    })
+}
+
+/// Convert an error into an `io::Error`, keeping its source chain.
+///
+/// The `io::Error` has the kind `ErrorKind::Other`, and its sources are the
+/// sources of `err`. Unlike the errors created by this crate, its default
+/// `Display` (and so `to_string()`) prints the whole chain, since most code
+/// handling an `io::Error` won't know to ask for it with `{:+}`.
+///
+/// *Requires the `std` feature.*
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+/// let io = errors::into_io(err);
+///
+/// assert_eq!(io.to_string(), "ship exploded: cat hair in generator");
+/// ```
+#[cfg(feature = "std")]
+pub fn into_io<E>(err: E) -> io::Error
+where
+    E: Into<BoxError>,
+{
+    into_io_with_kind(io::ErrorKind::Other, err)
+}
+
+/// Convert an error into an `io::Error` of a specific kind, keeping its
+/// source chain.
+///
+/// This is the same as [`errors::into_io`](into_io), with a custom
+/// `ErrorKind`.
+///
+/// *Requires the `std` feature.*
+#[cfg(feature = "std")]
+pub fn into_io_with_kind<E>(kind: io::ErrorKind, err: E) -> io::Error
+where
+    E: Into<BoxError>,
+{
+    io::Error::new(kind, IoSource(err.into()))
+}
+
+// The error inside an `io::Error` from `into_io`. The `io::Error` passes on
+// both its formatter and `source()`, so this prints only its own message when
+// a chain asks for it, and the whole chain otherwise.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct IoSource(BoxError);
+
+#[cfg(feature = "std")]
+impl fmt::Display for IoSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.sign_minus() || f.sign_plus() {
+            fmt::Display::fmt(&::fmt(&*self.0), f)
+        } else if f.alternate() {
+            write!(f, "{:+#}", ::fmt(&*self.0))
+        } else {
+            write!(f, "{:+}", ::fmt(&*self.0))
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for IoSource {
+    fn source(&self) -> Option<&ErrorRef> {
+        self.0.source()
+    }
+}
+
+/// Wrap a value as a new `Error`, hiding its source chain but keeping a tag.
+///
+/// This is the same as [`errors::opaque`](opaque), except that the `tag` can
+/// be retrieved with [`errors::opaque_tag`](opaque_tag). This allows checking
+/// for a coarse category of error, without exposing the types of the hidden
+/// chain.
+///
+/// # Example
+///
+/// ```
+/// #[derive(Debug, PartialEq)]
+/// struct RetriesExhausted;
+///
+/// let orig = errors::wrap("request failed", "timeout");
+/// let err = errors::opaque_tagged(orig, RetriesExhausted);
+///
+/// assert_eq!(errors::opaque_tag::<RetriesExhausted>(&err), Some(&RetriesExhausted));
+/// ```
+pub fn opaque_tagged<E, T>(err: E, tag: T) -> impl Error
+where
+    E: Into<BoxError>,
+    T: Send + Sync + 'static,
+{
+    Opaque {
+        inner: err.into(),
+        tag: Some(Box::new(tag)),
+        keep_root: false,
+    }
+}
+
+/// Wrap a value as a new `Error`, hiding its source chain except for the
+/// root.
+///
+/// This is the same as [`errors::opaque`](opaque), except that the `source`
+/// is the root of the hidden chain. The errors in between can't be matched
+/// on, but the underlying cause, such as an `io::Error`, still can. The whole
+/// chain is still printed.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use std::io;
+///
+/// let orig = errors::wrap("request failed", io::Error::other("timed out"));
+/// let err = errors::opaque_keep_root(orig);
+///
+/// assert_eq!(format!("{:+}", err), "request failed: timed out");
+/// assert_eq!(err.source().unwrap().to_string(), "timed out");
+/// assert!(errors::is::<io::Error>(&err));
+/// ```
+pub fn opaque_keep_root<E>(err: E) -> impl Error
+where
+    E: Into<BoxError>,
+{
+    Opaque {
+        inner: err.into(),
+        tag: None,
+        keep_root: true,
+    }
+}
+
+/// Find the tag of an [`errors::opaque_tagged`](opaque_tagged) error.
+///
+/// Searches the source chain of `err` for the first `Opaque` with a tag of
+/// type `T`.
+pub fn opaque_tag<T: 'static>(err: &ErrorRef) -> Option<&T> {
+    ::iter::find_map(err, |e| {
+        e.downcast_ref::<Opaque>()
+            .and_then(|op| op.tag.as_ref())
+            .and_then(|tag| tag.downcast_ref::<T>())
+    })
+}
+
+/// Returns whether an error hides its source chain.
+///
+/// That is, whether `err` was made with [`errors::opaque`](opaque), so that
+/// looking further with [`errors::find`](::find) or [`errors::is`](::is) is
+/// pointless. Only `err` itself is checked, not its sources.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::opaque(errors::wrap("request failed", "timeout"));
+/// assert!(errors::is_opaque(&err));
+///
+/// let err = errors::wrap("retry failed", err);
+/// assert!(!errors::is_opaque(&err));
+/// ```
+pub fn is_opaque(err: &ErrorRef) -> bool {
+    err.is::<Opaque>()
+}
+
+/// Get the name of the concrete type of an error, such as for tagging
+/// metrics.
+///
+/// For an error created by this crate, this is the type of its message, or
+/// the kind of [`errors::categorized`](categorized), and an [`Opaque`]
+/// reports the error it was made from. The message of a macro, such as
+/// [`errors::new!`](new!), is reported as `core::fmt::Arguments`, or as a
+/// `String` once formatted. Other errors can't be
+/// asked for their type, so only a few common ones from `core`, `alloc`, and
+/// `std` are recognized, and the rest are `"unknown"`.
+///
+/// The names come from `core::any::type_name`, so they are meant for
+/// diagnostics, and may change between versions of Rust.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap(String::from("ship exploded"), "cat hair in generator");
+/// assert_eq!(errors::type_name(&err), "alloc::string::String");
+///
+/// let err = errors::new("ship exploded");
+/// assert_eq!(errors::type_name(&err), "&str");
+/// ```
+pub fn type_name(err: &ErrorRef) -> &'static str {
+    fn known<E: Error + 'static>(err: &ErrorRef) -> Option<&'static str> {
+        if err.is::<E>() {
+            Some(core::any::type_name::<E>())
+        } else {
+            None
+        }
+    }
+
+    if let Some(header) = Header::of(err) {
+        return header.message_type;
+    }
+    if let Some(opaque) = err.downcast_ref::<Opaque>() {
+        return type_name(opaque.hidden());
+    }
+    if let Some(with) = err.downcast_ref::<WithFields>() {
+        return type_name(&*with.inner);
+    }
+    if let Some(attached) = err.downcast_ref::<Attached>() {
+        return type_name(&*attached.0.err);
+    }
+    if let Some(attachment) = err.downcast_ref::<Attachment>() {
+        return type_name(&*attachment.meta);
+    }
+
+    #[cfg(feature = "std")]
+    let std = known::<io::Error>(err);
+    #[cfg(not(feature = "std"))]
+    let std = None;
+
+    std.or_else(|| known::<fmt::Error>(err))
+        .or_else(|| known::<core::num::ParseIntError>(err))
+        .or_else(|| known::<core::num::ParseFloatError>(err))
+        .or_else(|| known::<core::str::Utf8Error>(err))
+        .or_else(|| known::<alloc::string::FromUtf8Error>(err))
+        .or_else(|| known::<::StaticError>(err))
+        .unwrap_or("unknown")
+}
+
+/// Split a source chain into its visible errors, and the error hidden by an
+/// [`Opaque`] at its end, if any.
+///
+/// The visible errors are those of [`errors::iter::chain`](::iter::chain),
+/// ending with the `Opaque` itself. The hidden error is the one it was made
+/// from, whose own sources can be walked from there. This is meant for
+/// diagnostics that show the hidden part separately.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("request failed", errors::opaque(errors::wrap("timed out", "no route")));
+///
+/// let (visible, hidden) = errors::split_opaque(&err);
+///
+/// assert_eq!(visible.len(), 2);
+/// assert_eq!(format!("{:+}", errors::fmt(hidden.unwrap())), "timed out: no route");
+/// ```
+pub fn split_opaque(err: &ErrorRef) -> (Vec<&ErrorRef>, Option<&ErrorRef>) {
+    let visible = ::iter::chain(err).collect::<Vec<_>>();
+    let hidden = visible
+        .last()
+        .and_then(|e| e.downcast_ref::<Opaque>())
+        .map(Opaque::hidden);
+    (visible, hidden)
+}
+
+/// Wrap several errors with some additional message.
+///
+/// Since an `Error` can only have one `source`, the first cause is used as
+/// the source. All the causes are listed when formatted with `{:+#}`, after
+/// the same "Caused by:" label, and indented by the width the same as the
+/// sources of other errors. The other formats behave the same as with
+/// [`errors::wrap`](wrap) using the first cause, as does `{:+#}` when the
+/// aggregate is only a source, or formatted through an adapter such as
+/// [`errors::fmt::with_caused_by`](::fmt::with_caused_by).
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+///
+/// let err = errors::aggregate("launch failed", vec![
+///     errors::new_boxed("engine stalled"),
+///     errors::wrap_boxed("radio silent", "antenna missing"),
+/// ]);
+///
+/// assert_eq!(err.source().unwrap().to_string(), "engine stalled");
+/// assert!(format!("{:+#}", err).ends_with("\
+/// Caused by:
+///     engine stalled
+///     radio silent: antenna missing"));
+/// ```
+#[track_caller]
+pub fn aggregate<D, I>(message: D, causes: I) -> impl Error
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+    I: IntoIterator,
+    I::Item: Into<BoxError>,
+{
+    Aggregate {
+        message,
+        causes: causes.into_iter().map(Into::into).collect(),
+        location: Frame::Location(Location::caller()),
+    }
+}
+
+/// Take ownership of the first error of type `E` in the source chain.
+///
+/// The chain is unwrapped through the errors created by this crate, dropping
+/// them along the way. If `E` isn't found, or can only be reached through an
+/// error not created by this crate, the original error is returned back.
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+///
+/// let err = errors::wrap_boxed("ruh roh", io::Error::other("boom"));
+///
+/// let io = errors::downcast::<io::Error>(err).unwrap();
+/// assert_eq!(io.to_string(), "boom");
+/// ```
+pub fn downcast<E: Error + 'static>(err: BoxError) -> Result<E, BoxError> {
+    // Check first that `E` can be reached only through wrappers, so the
+    // original can be returned untouched if not.
+    let mut next: &ErrorRef = &*err;
+    while !typed(next).is::<E>() {
+        match Header::of(next).and_then(|h| h.cause.as_ref()) {
+            Some(cause) => next = &**cause,
+            None => return Err(err),
+        }
+    }
+
+    let mut err = err;
+    loop {
+        err = match err.downcast::<E>() {
+            Ok(e) => return Ok(*e),
+            Err(err) => err,
+        };
+        // These only drop the fields or the attachment, since the check
+        // found `E` inside.
+        if err.is::<WithFields>() {
+            err = err.downcast::<WithFields>().map(|with| with.inner).unwrap_or_else(|err| err);
+        } else if err.is::<Attached>() {
+            err = err.downcast::<Attached>().map(|attached| attached.0.err).unwrap_or_else(|err| err);
+        } else {
+            err = Header::of_mut(&mut *err)
+                .and_then(|h| h.cause.take())
+                .expect("checked that E is reachable through wrappers");
+        }
+    }
+}
+
+/// Get the backtrace captured when an error was created by this crate.
+///
+/// Returns `None` if the error was not created by this crate, or if capturing
+/// backtraces was disabled, as determined by [`Backtrace::capture`].
+///
+/// *Requires the `backtrace` feature.*
+///
+/// # Example
+///
+/// ```
+/// let err = errors::new("sound the alarm");
+///
+/// if let Some(bt) = errors::backtrace(&err) {
+///     println!("{}", bt);
+/// }
+/// ```
+#[cfg(feature = "backtrace")]
+pub fn backtrace(err: &ErrorRef) -> Option<&Backtrace> {
+    let bt = &Header::of(err)?.backtrace;
+    match bt.status() {
+        BacktraceStatus::Captured => Some(bt),
+        _ => None,
+    }
+}
+
+/// Get the `tracing` span that was current when an error was created by this
+/// crate.
+///
+/// Returns `None` if the error was not created by this crate, or if there
+/// was no current span.
+///
+/// *Requires the `tracing` feature.*
+///
+/// # Example
+///
+/// ```
+/// let span = tracing::info_span!("launch");
+/// let err = span.in_scope(|| errors::new("sound the alarm"));
+///
+/// if let Some(span) = errors::span(&err) {
+///     println!("{:?}", span.metadata());
+/// }
+/// ```
+#[cfg(feature = "tracing")]
+pub fn span(err: &ErrorRef) -> Option<&tracing::Span> {
+    let span = &Header::of(err)?.span;
+    if span.is_none() {
+        None
+    } else {
+        Some(span)
+    }
+}
+
+/// Log an error as a `tracing` event at the `ERROR` level.
+///
+/// The event has an `error` field with the message of `err`, and a `causes`
+/// field with the messages of its sources, while the message of the event is
+/// the whole chain, like `{:+}`. If `err` was created in a span, as found by
+/// [`errors::span`](span), the event is emitted inside that span.
+///
+/// *Requires the `tracing` feature.*
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+///
+/// errors::emit(&err);
+/// ```
+#[cfg(feature = "tracing")]
+pub fn emit(err: &ErrorRef) {
+    let mut messages = ::iter::messages(err);
+    let error = messages.next().unwrap_or_default();
+    let causes = messages.collect::<Vec<_>>();
+    let event = || {
+        tracing::error!(error = %error, causes = ?causes, "{:+}", ::fmt(err));
+    };
+
+    match span(err) {
+        Some(span) => span.in_scope(event),
+        None => event(),
+    }
+}
+
+/// Log an error with the `log` crate at the `Error` level.
+///
+/// The message of the record is the whole chain, like `{:+}`.
+///
+/// *Requires the `log` feature.*
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+///
+/// errors::log_error(&err);
+/// ```
+#[cfg(feature = "log")]
+pub fn log_error(err: &ErrorRef) {
+    log::error!("{:+}", ::fmt(err));
+}
+
+/// Get the time when an error was created by this crate.
+///
+/// Returns `None` if the error was not created by this crate.
+///
+/// *Requires the `time` feature.*
+///
+/// # Example
+///
+/// ```
+/// use std::time::SystemTime;
+///
+/// let before = SystemTime::now();
+/// let err = errors::new("sound the alarm");
+///
+/// assert!(errors::created(&err).unwrap() >= before);
+/// ```
+#[cfg(feature = "time")]
+pub fn created(err: &ErrorRef) -> Option<SystemTime> {
+    Header::of(err).map(|h| h.created)
+}
+
+/// Format a message and source the same way as the errors of this crate.
+///
+/// This lets a custom error type get the same formatting behavior, such as
+/// `{:+}` printing the source chain, while still owning its own fields.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use std::fmt;
+///
+/// #[derive(Debug)]
+/// struct ConfigError {
+///     path: String,
+///     source: std::io::Error,
+/// }
+///
+/// impl fmt::Display for ConfigError {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         let msg = format!("failed to read {}", self.path);
+///         fmt::Display::fmt(&errors::from_parts(msg, self.source()), f)
+///     }
+/// }
+///
+/// impl Error for ConfigError {
+///     fn source(&self) -> Option<&(dyn Error + 'static)> {
+///         Some(&self.source)
+///     }
+/// }
+///
+/// let err = ConfigError {
+///     path: "ship.toml".into(),
+///     source: std::io::Error::other("not found"),
+/// };
+///
+/// assert_eq!(format!("{:+}", err), "failed to read ship.toml: not found");
+/// ```
+pub fn from_parts<'a, D>(message: D, source: Option<&'a ErrorRef>) -> impl fmt::Display + 'a
+where
+    D: fmt::Debug + fmt::Display + 'a,
+{
+    WrapperRef {
+        message,
+        cause: source,
+        location: None,
+        #[cfg(feature = "backtrace")]
+        backtrace: None,
+        #[cfg(feature = "time")]
+        created: None,
+        fields: &[],
+        separator: ": ",
+        caused_by: CAUSED_BY,
+        message_frame: false,
+    }
+}
+
+/// Attach a key/value field to an error.
+///
+/// Fields are printed in the alternate (`{:#}`) format, after the frame of
+/// the error they are attached to, and can be retrieved with
+/// [`errors::fields`](fields).
+///
+/// An error created by this crate stores the field itself. Any other error
+/// is first wrapped in an error that formats the same, and hides nothing.
+/// The original is still found by type, such as with
+/// [`errors::is`](::is) and [`errors::downcast`](downcast), but the boxed
+/// error itself will not downcast to it.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::with_field(errors::new("ship exploded"), "ship", "Nostromo");
+/// let err = errors::with_field(err, "crew", 7);
+///
+/// let fields = errors::fields(&*err)
+///     .map(|(k, v)| format!("{}={}", k, v))
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(fields, ["ship=Nostromo", "crew=7"]);
+/// ```
+pub fn with_field<E, V>(err: E, key: &'static str, value: V) -> BoxError
+where
+    E: Into<BoxError>,
+    V: fmt::Display + Send + Sync + 'static,
+{
+    let mut err = err.into();
+    let field = Field {
+        key,
+        value: Box::new(value),
+    };
+
+    if let Some(header) = Header::of_mut(&mut *err) {
+        header.fields.push(field);
+        return err;
+    }
+    if let Some(with) = err.downcast_mut::<WithFields>() {
+        with.fields.push(field);
+        return err;
+    }
+    Box::new(WithFields {
+        fields: alloc::vec![field],
+        inner: err,
+    })
+}
+
+/// Get an `Iterator` of the fields of every error in the source chain.
+///
+/// The fields of `err` come first, in the order they were attached, followed
+/// by those of each source.
+///
+/// # Example
+///
+/// ```
+/// let err = errors::with_field("connection reset", "request_id", "abc");
+/// let err = errors::wrap("request failed", err);
+///
+/// let (key, value) = errors::fields(&err).next().unwrap();
+/// assert_eq!((key, value.to_string().as_str()), ("request_id", "abc"));
+/// ```
+pub fn fields<'a>(err: &'a ErrorRef) -> impl Iterator<Item = (&'a str, &'a dyn fmt::Display)> + 'a {
+    ::iter::chain(err)
+        .flat_map(|e: &'a ErrorRef| fields_of(e))
+        .map(|field| (field.key, &*field.value as &dyn fmt::Display))
+}
+
+/// Build an error chain from its messages, top first.
+///
+/// The last message becomes the root error, and each message before it wraps
+/// the one after. This is the inverse of
+/// [`errors::iter::messages`](::iter::messages), such as to rebuild an error
+/// from a log. Returns `None` if there are no messages.
+///
+/// # Example
+///
+/// ```
+/// let msgs = vec!["ship exploded".to_string(), "cat hair in generator".to_string()];
+/// let err = errors::from_messages(msgs).unwrap();
+///
+/// assert_eq!(format!("{:+}", err), "ship exploded: cat hair in generator");
+/// ```
+#[track_caller]
+pub fn from_messages<I>(messages: I) -> Option<impl Error>
+where
+    I: IntoIterator<Item = String>,
+{
+    let location = Frame::Location(Location::caller());
+    let mut messages = messages.into_iter().collect::<Vec<_>>();
+    if messages.is_empty() {
+        return None;
+    }
+
+    let top = messages.remove(0);
+    let cause = messages.into_iter().rev().fold(None, |cause, message| {
+        Some(Box::new(Wrapper::at(message, cause, location)) as BoxError)
+    });
+    Some(Wrapper::at(top, cause, location))
+}
+
+/// Copy an error and its source chain into an owned error that is `Clone`.
+///
+/// The types of the errors can't be kept through a `dyn Error`, so each one
+/// is copied as its message. The locations of errors created by this crate
+/// are kept as well, so that the copy formats the same as the original,
+/// except for any backtraces and fields. The sources hidden by an
+/// [`errors::opaque`](opaque) error are copied too, but as plain sources, so
+/// they are no longer hidden from [`errors::iter::chain`](::iter::chain).
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("ship exploded", "cat hair in generator");
+/// let copy = errors::clone_chain(&err);
+/// drop(err);
+///
+/// let log = copy.clone();
+/// assert_eq!(format!("{:+}", log), "ship exploded: cat hair in generator");
+/// ```
+pub fn clone_chain(err: &ErrorRef) -> impl Error + Clone + Send + Sync + 'static {
+    fn snapshot(err: &ErrorRef, snapshots: &mut Vec<Snapshot>) {
+        for e in ::iter::chain(err) {
+            // An `Opaque` prints the top of its hidden chain, with its frame,
+            // followed by the rest of it.
+            let header = match e.downcast_ref::<Opaque>() {
+                Some(op) => Header::of(op.hidden()),
+                None => Header::of(e),
+            };
+            snapshots.push(Snapshot {
+                message: e.to_string(),
+                location: header.map(|h| h.location),
+                #[cfg(feature = "time")]
+                created: header.map(|h| h.created),
+                source: None,
+            });
+            if let Some(op) = e.downcast_ref::<Opaque>() {
+                if let Some(hidden) = op.hidden().source() {
+                    snapshot(hidden, snapshots);
+                }
+                return;
+            }
+        }
+    }
+
+    let mut snapshots = Vec::new();
+    snapshot(err, &mut snapshots);
+
+    let mut top = snapshots.pop().expect("errors::iter::chain always yields at least 1 item");
+    while let Some(mut next) = snapshots.pop() {
+        next.source = Some(Box::new(top));
+        top = next;
+    }
+    top
+}
+
+pub(crate) fn wrap_ref<'a>(err: &'a dyn Error) -> impl Error + 'a {
+    wrap_ref_separated(err, ": ")
+}
+
+pub(crate) fn wrap_ref_separated<'a>(err: &'a dyn Error, separator: &'a str) -> impl Error + 'a {
+    wrap_ref_labeled(err, separator, CAUSED_BY)
+}
+
+pub(crate) fn wrap_ref_labeled<'a>(
+    err: &'a dyn Error,
+    separator: &'a str,
+    caused_by: &'a str,
+) -> impl Error + 'a {
+    // `err` may not be `'static`, so it can't be checked for a `Wrapper`.
+    // Instead, it prints its own frame, the same as each of its sources.
+    WrapperRef {
+        message: err,
+        cause: err.source(),
+        location: None,
+        #[cfg(feature = "backtrace")]
+        backtrace: None,
+        #[cfg(feature = "time")]
+        created: None,
+        fields: &[],
+        separator,
+        caused_by,
+        message_frame: true,
+    }
+}
+
+/// Whether the error already follows the formatting flags of this crate.
+pub(crate) fn follows_format(err: &ErrorRef) -> bool {
+    err.is::<Wrapper>()
+        || err.is::<Opaque>()
+        || err.is::<WithFields>()
+        || err.is::<Attached>()
+        || err.is::<Attachment>()
+}
+
+/// Where the error was created, if it was created by this crate.
+pub(crate) fn location_of(err: &ErrorRef) -> Option<impl fmt::Display> {
+    if let Some(header) = Header::of(err) {
+        Some(header.location)
+    } else if let Some(opaque) = err.downcast_ref::<Opaque>() {
+        location_of(opaque.hidden())
+    } else if let Some(with) = err.downcast_ref::<WithFields>() {
+        location_of(&*with.inner)
+    } else if let Some(attached) = err.downcast_ref::<Attached>() {
+        location_of(&*attached.0.err)
+    } else if let Some(attachment) = err.downcast_ref::<Attachment>() {
+        location_of(&*attachment.meta)
+    } else {
+        None
+    }
+}
+
+/// The source of an error of this crate, which is known to be `Send + Sync`,
+/// since it is stored as a `BoxError`.
+pub(crate) fn source_send(err: &SendErrorRef) -> Option<&SendErrorRef> {
+    // Only a `Wrapper` itself, found by its type, has a `BoxError` cause.
+    // Any other error has sources that can't be made `Send + Sync` again.
+    if let Some(wrapper) = err.downcast_ref::<Wrapper>() {
+        wrapper.header.cause.as_deref()
+    } else if let Some(with) = err.downcast_ref::<WithFields>() {
+        source_send(&*with.inner)
+    } else {
+        None
+    }
+}
+
+/// The error to check the type of. Some errors of this crate stand in for
+/// another, such as one not created by this crate holding its fields, so
+/// that one is checked instead.
+pub(crate) fn typed(err: &ErrorRef) -> &ErrorRef {
+    if let Some(with) = err.downcast_ref::<WithFields>() {
+        typed(&*with.inner)
+    } else if let Some(attached) = err.downcast_ref::<Attached>() {
+        typed(&*attached.0.err)
+    } else if let Some(attachment) = err.downcast_ref::<Attachment>() {
+        typed(&*attachment.meta)
+    } else if let Some(src) = err.downcast_ref::<::Source>() {
+        typed(src.as_error())
+    } else {
+        err
+    }
+}
+
+fn fields_of(err: &ErrorRef) -> &[Field] {
+    if let Some(header) = Header::of(err) {
+        &header.fields
+    } else if let Some(with) = err.downcast_ref::<WithFields>() {
+        &with.fields
+    } else if let Some(attached) = err.downcast_ref::<Attached>() {
+        fields_of(&*attached.0.err)
+    } else if let Some(attachment) = err.downcast_ref::<Attachment>() {
+        fields_of(&*attachment.meta)
+    } else {
+        &[]
+    }
+}
+
+// The message is boxed, so that every `Wrapper` is the same type, and can
+// be found behind a `&dyn Error` with `downcast_ref::<Wrapper>()`. That is
+// one allocation for every message, even a `&'static str`, see
+// tests/alloc.rs. Only `errors::wrap_args` keeps its message inline, since it
+// borrows anyways.
+struct Wrapper<M = Box<dyn Message>> {
+    header: Header,
+    message: M,
+}
+
+trait Message: fmt::Debug + fmt::Display + Send + Sync + 'static {
+    // To find the kind of `errors::categorized`.
+    fn as_any(&self) -> &dyn Any;
+}
+
+struct Header {
+    cause: Option<BoxError>,
+    location: Frame,
+    #[cfg(feature = "backtrace")]
+    backtrace: Backtrace,
+    #[cfg(feature = "time")]
+    created: SystemTime,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    fields: Vec<Field>,
+    // The name of the type of the message, for `errors::type_name`.
+    message_type: &'static str,
+}
+
+struct Field {
+    key: &'static str,
+    value: Box<dyn fmt::Display + Send + Sync>,
+}
+
+// Holds the fields of an error not created by this crate.
+struct WithFields {
+    fields: Vec<Field>,
+    inner: BoxError,
+}
+
+// Made with `errors::attach`, which formats as `err`, with `meta` as its
+// source, followed by the sources of `err`.
+struct Attached(Attachment);
+
+// The source of an `Attached`.
+struct Attachment {
+    meta: BoxError,
+    err: BoxError,
+}
+
+// Put before each source in the alternate form, unless changed with
+// `errors::fmt::with_caused_by`.
+pub(crate) const CAUSED_BY: &str = "Caused by: ";
+
+
+struct WrapperRef<'a, D> {
+    message: D,
+    cause: Option<&'a ErrorRef>,
+    location: Option<Frame>,
+    #[cfg(feature = "backtrace")]
+    backtrace: Option<&'a Backtrace>,
+    #[cfg(feature = "time")]
+    created: Option<SystemTime>,
+    fields: &'a [Field],
+    separator: &'a str,
+    // Put before each source in the alternate form.
+    caused_by: &'a str,
+    // The message is an error that prints its own frame in the alternate
+    // form, the same as the sources do.
+    message_frame: bool,
+}
+
+// Where an error was created.
+#[derive(Clone, Copy)]
+enum Frame {
+    Location(&'static Location<'static>),
+    Label(&'static str),
+}
+
+struct Lazy<F>(F);
+
+// The message of `errors::categorized`.
+struct Kinded<K> {
+    kind: K,
+    message: Box<dyn fmt::Display + Send + Sync>,
+}
+
+// A message that prints without quotes in `Debug` as well.
+struct Static(&'static str);
+
+// A message formatted up front, that prints without quotes in `Debug` as
+// well.
+struct Formatted(Box<str>);
+
+#[derive(Clone)]
+struct Snapshot {
+    message: String,
+    location: Option<Frame>,
+    #[cfg(feature = "time")]
+    created: Option<SystemTime>,
+    source: Option<Box<Snapshot>>,
+}
+
+struct Aggregate<D> {
+    message: D,
+    causes: Vec<BoxError>,
+    location: Frame,
+}
+
+/// An error whose source chain has been hidden with [`errors::opaque`](opaque).
+///
+/// The formatting rules of this crate treat an `Opaque` as a barrier: its
+/// hidden chain is still printed, but it never reports a `source`, unless made
+/// with [`errors::opaque_keep_root`](opaque_keep_root). Matching
+/// on this type is how you detect a deliberately-opaque segment of a chain,
+/// such as in a custom formatter built on top of the iterators in
+/// [`errors::iter`](::iter).
+///
+/// # Example
+///
+/// ```
+/// let err = errors::wrap("too many attempts", errors::opaque("timed out"));
+///
+/// assert!(errors::is::<errors::Opaque>(&err));
+/// ```
+pub struct Opaque {
+    inner: BoxError,
+    tag: Option<Box<dyn Any + Send + Sync>>,
+    // Made with `errors::opaque_keep_root`, so the `source` is the root of
+    // the hidden chain.
+    keep_root: bool,
+}
+
+// ===== impl Wrapper =====
+
+impl Wrapper {
+    #[track_caller]
+    fn new<D: Message>(message: D, cause: Option<BoxError>) -> Wrapper {
+        Wrapper::at(message, cause, Frame::Location(Location::caller()))
+    }
+
+    fn at<D: Message>(message: D, cause: Option<BoxError>, location: Frame) -> Wrapper {
+        Wrapper::inline(Box::new(message), cause, location, core::any::type_name::<D>())
+    }
+
+    // For a message of a type private to this crate, reporting the type it
+    // was made from to `errors::type_name` instead.
+    #[track_caller]
+    fn named<D: Message>(message: D, cause: Option<BoxError>, message_type: &'static str) -> Wrapper {
+        let location = Frame::Location(Location::caller());
+        Wrapper::inline(Box::new(message), cause, location, message_type)
+    }
+}
+
+impl<M> Wrapper<M> {
+    fn inline(message: M, cause: Option<BoxError>, location: Frame, message_type: &'static str) -> Wrapper<M> {
+        Wrapper {
+            header: Header {
+                cause,
+                location,
+                #[cfg(feature = "backtrace")]
+                backtrace: Backtrace::capture(),
+                #[cfg(feature = "time")]
+                created: SystemTime::now(),
+                #[cfg(feature = "tracing")]
+                span: tracing::Span::current(),
+                fields: Vec::new(),
+                message_type,
+            },
+            message,
+        }
+    }
+}
+
+impl<M> Wrapper<M>
+where
+    M: fmt::Debug + fmt::Display,
+{
+    fn wrap_ref(&self) -> WrapperRef<'_, &M> {
+        WrapperRef {
+            message: &self.message,
+            cause: self.source(),
+            location: Some(self.header.location),
+            #[cfg(feature = "backtrace")]
+            backtrace: Some(&self.header.backtrace),
+            #[cfg(feature = "time")]
+            created: Some(self.header.created),
+            fields: &self.header.fields,
+            separator: ": ",
+            caused_by: CAUSED_BY,
+            message_frame: false,
+        }
+    }
+}
+
+impl<M> fmt::Debug for Wrapper<M>
+where
+    M: fmt::Debug + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.wrap_ref(), f)
+    }
+}
+
+impl<M> fmt::Display for Wrapper<M>
+where
+    M: fmt::Debug + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.wrap_ref(), f)
+    }
+}
+
+impl<M> Error for Wrapper<M>
+where
+    M: fmt::Debug + fmt::Display,
+{
+    fn source(&self) -> Option<&ErrorRef> {
+        self.header.cause.as_ref().map(|e| &**e as _)
+    }
+
+    // `Error::provide` is still unstable. Once it isn't, this should forward
+    // to the cause, so that what it provides is reachable through a wrap.
+}
+
+// ===== impl Header =====
+
+impl Header {
+    fn of(err: &ErrorRef) -> Option<&Header> {
+        err.downcast_ref::<Wrapper>().map(|w| &w.header)
+    }
+
+    fn of_mut(err: &mut SendErrorRef) -> Option<&mut Header> {
+        err.downcast_mut::<Wrapper>().map(|w| &mut w.header)
+    }
+}
+
+// ===== impl Message =====
+
+impl<D> Message for D
+where
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// ===== impl WrapperRef =====
+
+
+impl<'a, D> WrapperRef<'a, D>
+where
+    D: fmt::Debug + fmt::Display,
+{
+    fn write_joiner(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("\n")?;
+            f.write_str(self.caused_by)
+        } else {
+            f.write_str(self.separator)
+        }
+    }
+
+    fn fmt_all_sources(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (depth, err) in ::iter::sources(self).printed().enumerate() {
+            let depth = depth + 1;
+            if let Some(width) = indent_width(f) {
+                write_indented_joiner(f, width * depth, self.caused_by)?;
+            } else {
+                self.write_joiner(f)?;
+            }
+
+            // Propagate if chain ends in `Opaque`
+            if let Some(op) = err.downcast_ref::<Opaque>() {
+                let op = op.wrap_ref_labeled(self.separator, self.caused_by);
+                return if let Some(width) = indent_width(f) {
+                    write!(Indent::new(f, width * depth), "{:+#1$}", op, width)
+                } else if f.alternate() {
+                    write!(f, "{:+#}", op)
+                } else {
+                    write!(f, "{:+}", op)
+                };
+            }
+
+            // else
+            if let Some(width) = indent_width(f) {
+                write!(Indent::new(f, width * depth), "{:-#}", err)?;
+            } else if f.alternate() {
+                write!(f, "{:-#}", err)?;
+            } else {
+                write!(f, "{:-}", err)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fmt_max_sources(&self, f: &mut fmt::Formatter, mut max: usize) -> fmt::Result {
+        let mut sources = ::iter::sources(self).printed();
+        let mut depth = 0;
+        loop {
+            if max == 0 {
+                return Ok(());
+            }
+            // `max` is now how many more sources may follow this one, which
+            // is exactly the precision to give an `Opaque` source below.
+            max -= 1;
+            depth += 1;
+
+            let err = match sources.next() {
+                Some(err) => err,
+                None => break,
+            };
+
+            if let Some(width) = indent_width(f) {
+                write_indented_joiner(f, width * depth, self.caused_by)?;
+            } else {
+                self.write_joiner(f)?;
+            }
+
+            // Propagate if chain ends in `Opaque`
+            if let Some(op) = err.downcast_ref::<Opaque>() {
+                let op = op.wrap_ref_labeled(self.separator, self.caused_by);
+                return if let Some(width) = indent_width(f) {
+                    write!(Indent::new(f, width * depth), "{:+#1$.2$}", op, width, max)
+                } else if f.alternate() {
+                    write!(f, "{:+#.*}", max, op)
+                } else {
+                    write!(f, "{:+.*}", max, op)
+                };
+            }
+
+            //else
+            if let Some(width) = indent_width(f) {
+                write!(Indent::new(f, width * depth), "{:-#}", err)?;
+            } else if f.alternate() {
+                write!(f, "{:-#}", err)?;
+            } else {
+                write!(f, "{:-}", err)?;
+            }
+
+        }
+
+        Ok(())
+    }
+
+    fn fmt_frame(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !f.alternate() {
+            return Ok(());
+        }
+
+        if let Some(frame) = self.location {
+            write!(f, "\n    at {}", frame)?;
+        }
+
+        #[cfg(feature = "time")]
+        {
+            if let Some(created) = self.created {
+                write!(f, "\n    at {}", Timestamp(created))?;
+            }
+        }
+
+        for field in self.fields {
+            write!(f, "\n    {}={}", field.key, field.value)?;
+        }
+
+        #[cfg(feature = "backtrace")]
+        {
+            if let Some(bt) = self.backtrace {
+                if let BacktraceStatus::Captured = bt.status() {
+                    write!(f, "\n{}", bt)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// ===== impl Snapshot =====
+
+impl Snapshot {
+    fn wrap_ref(&self) -> WrapperRef<'_, &String> {
+        WrapperRef {
+            message: &self.message,
+            cause: self.source(),
+            location: self.location,
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+            #[cfg(feature = "time")]
+            created: self.created,
+            fields: &[],
+            separator: ": ",
+            caused_by: CAUSED_BY,
+            message_frame: false,
+        }
+    }
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.wrap_ref(), f)
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.wrap_ref(), f)
+    }
+}
+
+impl Error for Snapshot {
+    fn source(&self) -> Option<&ErrorRef> {
+        self.source.as_ref().map(|s| &**s as &ErrorRef)
+    }
+}
+
+// ===== impl Lazy =====
+
+impl<F> fmt::Debug for Lazy<F>
+where
+    F: Fn(&mut fmt::Formatter) -> fmt::Result,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.0)(f)
+    }
+}
+
+impl<F> fmt::Display for Lazy<F>
+where
+    F: Fn(&mut fmt::Formatter) -> fmt::Result,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.0)(f)
+    }
+}
+
+// ===== impl Frame =====
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Frame::Location(loc) => write!(f, "{}:{}", loc.file(), loc.line()),
+            Frame::Label(label) => f.write_str(label),
+        }
+    }
+}
+
+// ===== impl Kinded =====
+
+impl<K: fmt::Debug> fmt::Debug for Kinded<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<K: fmt::Debug> fmt::Display for Kinded<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:?}] {}", self.kind, self.message)
+    }
+}
+
+// ===== impl Static =====
+
+impl fmt::Debug for Static {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+impl fmt::Display for Static {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+impl fmt::Debug for Formatted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Formatted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Formats a `SystemTime` as an RFC 3339 UTC timestamp, to the second.
+#[cfg(feature = "time")]
+struct Timestamp(SystemTime);
+
+#[cfg(feature = "time")]
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = match self.0.duration_since(UNIX_EPOCH) {
+            Ok(dur) => dur.as_secs(),
+            // before 1970, not worth a calendar
+            Err(_) => return fmt::Debug::fmt(&self.0, f),
+        };
+
+        let days = secs / 86_400;
+        let rem = secs % 86_400;
+
+        // Howard Hinnant's `civil_from_days`, for days since 1970-01-01.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60,
+        )
+    }
+}
+
+/// The width flag of `{:+#N}` indents each cause by `N` more spaces.
+fn indent_width(f: &fmt::Formatter) -> Option<usize> {
+    match f.width() {
+        Some(width) if f.alternate() && width > 0 => Some(width),
+        _ => None,
+    }
+}
+
+fn write_indented_joiner(f: &mut fmt::Formatter, indent: usize, caused_by: &str) -> fmt::Result {
+    write!(f, "\n{:1$}{2}", "", indent, caused_by)
+}
+
+/// Indents every new line written through it.
+struct Indent<'a, 'b: 'a> {
+    f: &'a mut fmt::Formatter<'b>,
+    indent: usize,
+}
+
+impl<'a, 'b> Indent<'a, 'b> {
+    fn new(f: &'a mut fmt::Formatter<'b>, indent: usize) -> Self {
+        Indent { f, indent }
+    }
+}
+
+impl<'a, 'b> fmt::Write for Indent<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.f.write_str(first)?;
+        }
+        for line in lines {
+            write!(self.f, "\n{:1$}{2}", "", self.indent, line)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, D: fmt::Debug> fmt::Debug for WrapperRef<'a, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // {:-?} means only the message, see below
+        let cause = match self.cause {
+            Some(cause) if !f.sign_minus() => cause,
+            _ => return fmt::Debug::fmt(&self.message, f),
+        };
+
+        // Prints the same as nested `debug_tuple`s, `(message, cause)`, but
+        // walks the chain here instead of each cause printing its own cause,
+        // so that a deep chain can't overflow the stack.
+        let pretty = f.alternate();
+        f.write_str(if pretty { "(\n" } else { "(" })?;
+        let mut depth = 1;
+        debug_field(f, depth, &self.message, false)?;
+
+        let mut next = cause;
+        while let (true, Some(source)) = (next.is::<Wrapper>(), next.source()) {
+            if pretty {
+                writeln!(f, "{:1$}(", "", depth * 4)?;
+            } else {
+                f.write_str("(")?;
+            }
+            depth += 1;
+            debug_field(f, depth, &MessageDebug(next), false)?;
+            next = source;
+        }
+        debug_field(f, depth, &next, true)?;
+
+        while depth > 0 {
+            depth -= 1;
+            if pretty {
+                write!(f, "{:1$})", "", depth * 4)?;
+                if depth > 0 {
+                    f.write_str(",\n")?;
+                }
+            } else {
+                f.write_str(")")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn debug_field(f: &mut fmt::Formatter, depth: usize, value: &dyn fmt::Debug, last: bool) -> fmt::Result {
+    if f.alternate() {
+        write!(f, "{:1$}", "", depth * 4)?;
+        write!(Indent::new(f, depth * 4), "{:#?}", value)?;
+        f.write_str(",\n")
+    } else {
+        write!(f, "{:?}", value)?;
+        if last {
+            Ok(())
+        } else {
+            f.write_str(", ")
+        }
+    }
+}
+
+// Debug of only the message of a `Wrapper`.
+struct MessageDebug<'a>(&'a ErrorRef);
+
+impl<'a> fmt::Debug for MessageDebug<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{:-#?}", self.0)
+        } else {
+            write!(f, "{:-?}", self.0)
+        }
+    }
+}
+
+impl<'a, D> fmt::Display for WrapperRef<'a, D>
+where
+    D: fmt::Debug + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // {:+} means print the chain
+        if f.sign_plus() {
+            // first message with no flags...
+            if self.message_frame && f.alternate() {
+                write!(f, "{:-#}", self.message)?;
+            } else {
+                write!(f, "{:-}", self.message)?;
+            }
+            // {:#} means print the frame...
+            self.fmt_frame(f)?;
+            // precision flag signals max source chain iteration...
+            if let Some(max) = f.precision() {
+                self.fmt_max_sources(f, max)
+            } else {
+                self.fmt_all_sources(f)
+            }
+        } else if self.message_frame && f.alternate() {
+            write!(f, "{:#}", self.message)
+        } else {
+            // reset all formatter flags
+            write!(f, "{}", self.message)?;
+            self.fmt_frame(f)
+        }
+    }
+}
+
+impl<'a, D> Error for WrapperRef<'a, D>
+where
+    D: fmt::Debug + fmt::Display,
+{
+    fn source(&self) -> Option<&ErrorRef> {
+        self.cause.as_ref().map(|e| *e)
+    }
+}
+
+// ===== impl Aggregate =====
+
+impl<D> Aggregate<D>
+where
+    D: fmt::Debug + fmt::Display + 'static,
+{
+    fn wrap_ref(&self) -> WrapperRef<'_, &D> {
+        WrapperRef {
+            message: &self.message,
+            cause: self.source(),
+            location: Some(self.location),
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+            #[cfg(feature = "time")]
+            created: None,
+            fields: &[],
+            separator: ": ",
+            caused_by: CAUSED_BY,
+            message_frame: false,
+        }
+    }
+}
+
+impl<D> fmt::Debug for Aggregate<D>
+where
+    D: fmt::Debug + fmt::Display + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.causes.is_empty() {
+            fmt::Debug::fmt(&self.message, f)
+        } else {
+            f.debug_tuple("")
+                .field(&self.message)
+                .field(&self.causes)
+                .finish()
+        }
+    }
+}
+
+impl<D> fmt::Display for Aggregate<D>
+where
+    D: fmt::Debug + fmt::Display + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // only {:+#} lists every cause
+        if !(f.sign_plus() && f.alternate()) {
+            return fmt::Display::fmt(&self.wrap_ref(), f);
+        }
+
+        let top = self.wrap_ref();
+        write!(f, "{:-}", self.message)?;
+        top.fmt_frame(f)?;
+
+        let max = f.precision();
+        if max == Some(0) || self.causes.is_empty() {
+            return Ok(());
+        }
+
+        // The label is put on its own line, indented like the joiner of a
+        // source, with each cause one level deeper.
+        let (indent, cause_indent) = match indent_width(f) {
+            Some(width) => (width, width * 2),
+            None => (0, 4),
+        };
+        write!(f, "\n{:1$}{2}", "", indent, top.caused_by.trim_end())?;
+        for cause in &self.causes {
+            write!(f, "\n{:1$}", "", cause_indent)?;
+            match max {
+                Some(max) => write!(Indent::new(f, cause_indent), "{:+.*}", max - 1, cause)?,
+                None => write!(Indent::new(f, cause_indent), "{:+}", cause)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<D> Error for Aggregate<D>
+where
+    D: fmt::Debug + fmt::Display + 'static,
+{
+    fn source(&self) -> Option<&ErrorRef> {
+        self.causes.first().map(|e| &**e as _)
+    }
+}
+
+// ===== impl WithFields =====
+
+impl WithFields {
+    fn wrap_ref(&self) -> WrapperRef<'_, &ErrorRef> {
+        let header = Header::of(&*self.inner);
+        WrapperRef {
+            message: &*self.inner,
+            cause: self.inner.source(),
+            location: header.map(|h| h.location),
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+            #[cfg(feature = "time")]
+            created: None,
+            fields: &self.fields,
+            separator: ": ",
+            caused_by: CAUSED_BY,
+            message_frame: false,
+        }
+    }
+}
+
+impl fmt::Debug for WithFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Display for WithFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.wrap_ref(), f)
+    }
+}
+
+impl Error for WithFields {
+    fn source(&self) -> Option<&ErrorRef> {
+        self.inner.source()
+    }
+}
+
+// ===== impl Attached =====
+
+impl Attached {
+    fn wrap_ref(&self) -> WrapperRef<'_, &ErrorRef> {
+        // Both `err` and `meta` may be errors of this crate, which print
+        // their own frame.
+        WrapperRef {
+            message: &*self.0.err,
+            cause: Some(&self.0),
+            location: None,
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+            #[cfg(feature = "time")]
+            created: None,
+            fields: &[],
+            separator: ": ",
+            caused_by: CAUSED_BY,
+            message_frame: true,
+        }
+    }
+}
+
+impl fmt::Debug for Attached {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("")
+            .field(&self.0.err)
+            .field(&self.0.meta)
+            .finish()
+    }
+}
+
+impl fmt::Display for Attached {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.wrap_ref(), f)
+    }
+}
+
+impl Error for Attached {
+    fn source(&self) -> Option<&ErrorRef> {
+        Some(&self.0)
+    }
+}
+
+impl Attachment {
+    fn wrap_ref(&self) -> WrapperRef<'_, &ErrorRef> {
+        WrapperRef {
+            message: &*self.meta,
+            cause: self.err.source(),
+            location: None,
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+            #[cfg(feature = "time")]
+            created: None,
+            fields: &[],
+            separator: ": ",
+            caused_by: CAUSED_BY,
+            message_frame: true,
+        }
+    }
+}
+
+impl fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.meta, f)
+    }
+}
+
+impl fmt::Display for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.wrap_ref(), f)
+    }
+}
+
+impl Error for Attachment {
+    fn source(&self) -> Option<&ErrorRef> {
+        self.err.source()
+    }
+}
+
+// ===== impl Opaque =====
+
+impl Opaque {
+    pub(crate) fn hidden(&self) -> &ErrorRef {
+        &*self.inner
+    }
+
+    fn wrap_ref(&self) -> WrapperRef<'_, &ErrorRef> {
+        self.wrap_ref_labeled(": ", CAUSED_BY)
+    }
+
+    fn wrap_ref_labeled<'a>(&'a self, separator: &'a str, caused_by: &'a str) -> WrapperRef<'a, &'a ErrorRef> {
+        WrapperRef {
+            message: &*self.inner,
+            cause: self.inner.source(),
+            location: None,
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+            #[cfg(feature = "time")]
+            created: None,
+            fields: &[],
+            separator,
+            caused_by,
+            message_frame: true,
+        }
+    }
+}
+
+impl fmt::Debug for Opaque {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Display for Opaque {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Without {:+}, only the top message of the hidden error, same as an
+        // error with no source, even if its own `{}` would print more.
+        if f.sign_plus() {
+            fmt::Display::fmt(&self.wrap_ref(), f)
+        } else {
+            let top = WrapperRef {
+                cause: None,
+                ..self.wrap_ref()
+            };
+            match (f.alternate(), f.width()) {
+                (true, Some(width)) => write!(f, "{:+#1$}", top, width),
+                (true, None) => write!(f, "{:+#}", top),
+                (false, Some(width)) => write!(f, "{:+1$}", top, width),
+                (false, None) => write!(f, "{:+}", top),
+            }
+        }
+    }
+}
+
+// No source chains for opaque errors, besides a kept root! Nor, once
+// `Error::provide` is stable, anything the hidden errors provide.
+impl Error for Opaque {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        // Without a source, the root is the hidden error itself, which is
+        // already printed as this one.
+        if self.keep_root && self.inner.source().is_some() {
+            Some(::iter::root(&*self.inner))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    // Whether the chain has an error of this crate with a message of type `D`.
+    fn has_message<D>(err: &::ErrorRef) -> bool {
+        ::iter::chain(err).any(|e| {
+            super::Header::of(e).is_some_and(|h| h.message_type == core::any::type_name::<D>())
+        })
+    }
+
+    // The alternate form of `err` after its message, when it was created on
+    // `line` of this file. Timestamps depend on the clock, see tests/time.rs,
+    // and backtraces on the environment, see tests/backtrace.rs, so this only
+    // checks they are printed in place.
+    fn frame(err: &::ErrorRef, line: u32) -> String {
+        frame_at(err, &format!("{}:{}", file!(), line))
+    }
+
+    fn frame_at(err: &::ErrorRef, at: &str) -> String {
+        let frame = format!("\n    at {}", at);
+        #[cfg(feature = "time")]
+        let frame = match super::created(err) {
+            Some(created) => format!("{}\n    at {}", frame, super::Timestamp(created)),
+            None => frame,
+        };
+        let frame = super::fields_of(err)
+            .iter()
+            .fold(frame, |frame, field| format!("{}\n    {}={}", frame, field.key, field.value));
+        #[cfg(feature = "backtrace")]
+        let frame = match super::backtrace(err) {
+            Some(bt) => format!("{}\n{}", frame, bt),
+            None => frame,
+        };
+        let _ = err;
+        frame
+    }
+
+    #[test]
+    fn display_default() {
+        let cause = "cat hair in generator";
+        let top = "ship exploded";
+
+        let op = super::new(cause);
+        assert_eq!(format!("{}", op), cause);
+
+        let wp = super::wrap(top, cause);
+        assert_eq!(format!("{}", wp), top);
+
+        let wp_op = super::wrap(top, op);
+        assert_eq!(format!("{}", wp_op), top);
+    }
+
+    #[test]
+    fn display_chain() {
+        let cause = "cat hair in generator";
+        let top = "ship exploded";
+
+        let op = super::new(cause);
+        assert_eq!(format!("{:+}", op), cause);
+
+        let wp = super::wrap(top, cause);
+        assert_eq!(format!("{:+}", wp), format!("{}: {}", top, cause));
+
+        let wp_op = super::wrap(top, op);
+        assert_eq!(format!("{:+}", wp_op), format!("{}: {}", top, cause));
+    }
+
+    #[test]
+    fn display_chain_when_message_is_wrapped() {
+
+        let msg = super::wrap("b", "a");
+        let err = super::wrap(msg, "z");
+
+        assert_eq!(format!("{:+}", err), "b: z");
+    }
+
+    #[test]
+    fn display_alternative() {
+        let cause = "cat hair in generator";
+        let top = "ship exploded";
+
+        let (op, op_line) = (super::new(cause), line!());
+        let op_frame = format!("{}{}", cause, frame(&op, op_line));
+        assert_eq!(format!("{:#}", op), op_frame);
+        assert_eq!(format!("{:+#}", op), op_frame);
+
+        let (wp, wp_line) = (super::wrap(top, cause), line!());
+        let wp_frame = format!("{}{}", top, frame(&wp, wp_line));
+        assert_eq!(format!("{:#}", wp), wp_frame);
+        assert_eq!(format!("{:+#}", wp), format!("{}\nCaused by: {}", wp_frame, cause));
+
+        let (wp_op, wp_op_line) = (super::wrap(top, op), line!());
+        let alt = format!("{}{}\nCaused by: {}", top, frame(&wp_op, wp_op_line), op_frame);
+        assert_eq!(format!("{:+#}", wp_op), alt);
+    }
+
+    #[test]
+    fn display_alternative_without_location() {
+        use std::io;
+
+        let err = io::Error::other("cat hair in generator");
+        assert_eq!(format!("{:#}", ::fmt(&err)), "cat hair in generator");
+        assert_eq!(format!("{:+#}", ::fmt(&err)), "cat hair in generator");
+    }
+
+    #[test]
+    fn display_alternative_custom_frame() {
+        let err = super::wrap_at("fetch failed", "timed out", "task fetch_user#42");
+        let err_frame = format!("fetch failed{}", frame_at(&err, "task fetch_user#42"));
+        assert_eq!(format!("{}", err), "fetch failed");
+        assert_eq!(format!("{:#}", err), err_frame);
+        assert_eq!(format!("{:+#}", err), format!("{}\nCaused by: timed out", err_frame));
+
+        let (err, line) = (super::wrap("retry failed", err), line!());
+        assert_eq!(
+            format!("{:+#}", err),
+            format!(
+                "retry failed{}\nCaused by: {}\nCaused by: timed out",
+                frame(&err, line),
+                err_frame,
+            ),
+        );
+    }
+
+    #[test]
+    fn display_chain_max() {
+        let a = "a";
+        let op = super::new(a);
+        assert_eq!(format!("{:.0}", op), a);
+        assert_eq!(format!("{:.1}", op), a);
+        assert_eq!(format!("{:+.0}", op), a);
+        assert_eq!(format!("{:+.1}", op), a);
+
+        let wp = super::wrap("b", "a");
+        assert_eq!(format!("{:.0}", wp), "b");
+        assert_eq!(format!("{:.1}", wp), "b");
+        assert_eq!(format!("{:+.0}", wp), "b");
+        assert_eq!(format!("{:+.1}", wp), "b: a");
+
+        let wp2 = super::wrap("c", wp);
+        assert_eq!(format!("{:.0}", wp2), "c");
+        assert_eq!(format!("{:.1}", wp2), "c");
+        assert_eq!(format!("{:+.0}", wp2), "c");
+        assert_eq!(format!("{:+.1}", wp2), "c: b");
+        assert_eq!(format!("{:+.2}", wp2), "c: b: a");
+    }
+
+    // wrap_with()
+
+    #[test]
+    fn wrap_with_is_lazy() {
+        use std::cell::Cell;
+
+        let called = Cell::new(false);
+        let res: Result<(), &str> = Ok(());
+        let res = res.map_err(|e| super::wrap_with(|| {
+            called.set(true);
+            "ship exploded"
+        }, e));
+
+        assert!(res.is_ok());
+        assert!(!called.get());
+    }
+
+    #[test]
+    fn wrap_with_matches_wrap() {
+        use core::error::Error;
+
+        let wp = super::wrap("ship exploded", "cat hair in generator");
+        let ww = super::wrap_with(|| "ship exploded", "cat hair in generator");
+
+        assert_eq!(format!("{}", ww), format!("{}", wp));
+        assert_eq!(format!("{:+}", ww), format!("{:+}", wp));
+        assert_eq!(
+            ww.source().unwrap().to_string(),
+            wp.source().unwrap().to_string()
+        );
+    }
+
+    // new_boxed() / wrap_boxed()
+
+    #[test]
+    fn boxed_display_matches() {
+        let op = super::new("a");
+        let op_boxed = super::new_boxed("a");
+        assert_eq!(format!("{}", op_boxed), format!("{}", op));
+        assert_eq!(format!("{:+}", op_boxed), format!("{:+}", op));
+
+        let wp = super::wrap("c", super::wrap("b", "a"));
+        let wp_boxed = super::wrap_boxed("c", super::wrap_boxed("b", "a"));
+        assert_eq!(format!("{}", wp_boxed), format!("{}", wp));
+        assert_eq!(format!("{:+}", wp_boxed), format!("{:+}", wp));
+        assert_eq!(format!("{:+.1}", wp_boxed), format!("{:+.1}", wp));
+    }
+
+    // from_parts()
+
+    #[derive(Debug)]
+    struct Custom {
+        source: Option<::BoxError>,
+    }
+
+    impl fmt::Display for Custom {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            use core::error::Error;
+
+            fmt::Display::fmt(&super::from_parts("ship exploded", self.source()), f)
+        }
+    }
+
+    impl ::core::error::Error for Custom {
+        fn source(&self) -> Option<&::ErrorRef> {
+            self.source.as_ref().map(|e| &**e as _)
+        }
+    }
+
+    #[test]
+    fn from_parts_matches_wrap() {
+        let custom = Custom {
+            source: Some(super::wrap("cat hair", "in generator").into()),
+        };
+        let wp = super::wrap("ship exploded", super::wrap("cat hair", "in generator"));
+
+        assert_eq!(format!("{}", custom), format!("{}", wp));
+        assert_eq!(format!("{:+}", custom), format!("{:+}", wp));
+        assert_eq!(format!("{:+.1}", custom), format!("{:+.1}", wp));
+        assert_eq!(format!("{:+}", ::fmt(&custom)), format!("{:+}", wp));
+
+        let custom = Custom { source: None };
+        assert_eq!(format!("{:+#}", custom), "ship exploded");
+    }
+
+    #[test]
+    fn display_alternative_width_indents() {
+        let (a, a_line) = (super::new("a"), line!());
+        let a_frame = frame(&a, a_line).replace('\n', "\n    ");
+        let (b, b_line) = (super::wrap("b", a), line!());
+        let b_frame = frame(&b, b_line).replace('\n', "\n  ");
+        let (c, c_line) = (super::wrap("c", b), line!());
+        let c_frame = frame(&c, c_line);
+
+        let expected = format!("c{}\n  Caused by: b{}\n    Caused by: a{}", c_frame, b_frame, a_frame);
+        assert_eq!(format!("{:+#2}", c), expected);
+
+        let expected = format!("c{}\n  Caused by: b{}", c_frame, b_frame);
+        assert_eq!(format!("{:+#2.1}", c), expected);
+
+        // inline form is unchanged
+        assert_eq!(format!("{:+2}", c), "c: b: a");
+    }
+
+    #[test]
+    fn typed_matches_untyped() {
+        let err = super::wrap_typed("ship exploded", super::new_typed("cat hair in generator").into_inner());
+        assert_eq!(format!("{:+}", err), "ship exploded: cat hair in generator");
+
+        let err = super::opaque_typed(err.into_inner());
+        assert_eq!(format!("{:+}", err), "ship exploded: cat hair in generator");
+        assert!(err.source().is_none());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn timestamp_is_rfc3339() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use super::Timestamp;
+
+        let t = |secs| Timestamp(UNIX_EPOCH + Duration::from_secs(secs)).to_string();
+        assert_eq!(t(0), "1970-01-01T00:00:00Z");
+        assert_eq!(t(1_704_164_645), "2024-01-02T03:04:05Z");
+        assert_eq!(t(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn categorized_kind_through_wrap() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        enum Kind {
+            Network,
+            Config,
+        }
+
+        let err = super::categorized(Kind::Config, "missing ship.toml");
+        assert_eq!(err.to_string(), "[Config] missing ship.toml");
+        assert_eq!(super::kind_of::<Kind>(&err), Some(Kind::Config));
+
+        let err = super::wrap("launch failed", super::categorized(Kind::Network, "connection reset"));
+        assert_eq!(format!("{:+}", err), "launch failed: [Network] connection reset");
+        assert_eq!(super::kind_of::<Kind>(&err), Some(Kind::Network));
+
+        // other kind types, and uncategorized errors
+        assert_eq!(super::kind_of::<u8>(&err), None);
+        assert_eq!(super::kind_of::<Kind>(&super::wrap("b", "a")), None);
+    }
+
+    #[test]
+    fn debug_shape() {
+        use std::io;
+
+        let err = super::wrap("c", super::wrap("b", "a"));
+        assert_eq!(format!("{:?}", err), format!("{:?}", ("c", ("b", "a"))));
+        assert_eq!(format!("{:#?}", err), format!("{:#?}", ("c", ("b", "a"))));
+        assert_eq!(format!("{:?}", super::new("a")), format!("{:?}", "a"));
+
+        let io = || io::Error::new(io::ErrorKind::NotFound, "a");
+        let err = super::wrap("c", super::wrap("b", io()));
+        assert_eq!(format!("{:?}", err), format!("{:?}", ("c", ("b", io()))));
+        assert_eq!(format!("{:#?}", err), format!("{:#?}", ("c", ("b", io()))));
+    }
+
+    #[test]
+    fn debug_deep_chain() {
+        let mut err = super::new_boxed("0");
+        for i in 1..10_000 {
+            err = super::wrap_boxed(i, err);
+        }
+
+        let debug = format!("{:?}", err);
+        assert!(debug.starts_with("(9999, (9998, "), "{}", &debug[..20]);
+        assert!(debug.ends_with(&format!("(1, \"0\"){}", ")".repeat(9_998))));
+    }
+
+    #[test]
+    fn msg() {
+        let mut message = String::with_capacity(64);
+        message.push_str("ship \"Nostromo\" exploded");
+
+        let err: ::BoxError = super::msg(message.clone());
+        assert_eq!(err.to_string(), super::new(message.clone()).to_string());
+        assert_eq!(format!("{:?}", err), format!("{:?}", super::new(message)));
+        assert!(has_message::<Box<str>>(&*err));
+
+        let err = super::wrap("launch failed", err);
+        assert_eq!(format!("{:+}", err), "launch failed: ship \"Nostromo\" exploded");
+    }
+
+    #[test]
+    fn new_static_debug_unquoted() {
+        let msg = "ship \"Nostromo\" exploded";
+        assert_eq!(format!("{:?}", super::new(msg)), "\"ship \\\"Nostromo\\\" exploded\"");
+        assert_eq!(format!("{:?}", super::new_static(msg)), msg);
+
+        let err = super::new_static(msg);
+        assert_eq!(err.to_string(), super::new(msg).to_string());
+        let err = super::wrap("launch failed", err);
+        assert_eq!(format!("{:+}", err), "launch failed: ship \"Nostromo\" exploded");
+    }
+
+    #[test]
+    fn lazy_renders_each_format() {
+        use alloc::sync::Arc;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let err = super::lazy(move |f| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            f.write_str("ship exploded")
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        assert_eq!(err.to_string(), "ship exploded");
+        assert_eq!(err.to_string(), "ship exploded");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let err = super::wrap("c", err);
+        assert_eq!(format!("{:+}", err), "c: ship exploded");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn into_io_keeps_chain() {
+        use core::error::Error;
+        use std::io;
+
+        let io = super::into_io(::wrap("ship exploded", ::wrap("generator", "cat hair")));
+        assert_eq!(io.kind(), io::ErrorKind::Other);
+        assert_eq!(io.to_string(), "ship exploded: generator: cat hair");
+        assert_eq!(io.source().unwrap().to_string(), "generator");
+        assert_eq!(format!("{:+}", ::fmt(&io)), "ship exploded: generator: cat hair");
+        assert_eq!(format!("{:+.1}", ::fmt(&io)), "ship exploded: generator");
+
+        let io = super::into_io_with_kind(io::ErrorKind::NotFound, "ship.toml");
+        assert_eq!(io.kind(), io::ErrorKind::NotFound);
+        assert_eq!(io.to_string(), "ship.toml");
+    }
+
+    #[test]
+    fn attach_keeps_message() {
+        use core::error::Error;
+        use std::io;
+
+        let err = super::attach(::new("ship exploded"), io::Error::other("sensor 3"));
+        assert_eq!(err.to_string(), "ship exploded");
+        assert!(::is::<io::Error>(&err));
+        assert_eq!(format!("{:+}", err), "ship exploded: sensor 3");
+
+        let err = super::attach(io::Error::other("timed out"), "request #7");
+        assert_eq!(err.to_string(), "timed out");
+        assert_eq!(err.source().unwrap().to_string(), "request #7");
+    }
+
+    #[test]
+    fn attach_keeps_original() {
+        use std::io;
+
+        #[derive(Debug)]
+        struct RequestId(u64);
+
+        impl fmt::Display for RequestId {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "request #{}", self.0)
+            }
+        }
+
+        impl core::error::Error for RequestId {}
+
+        let err = super::wrap_boxed("connection reset", io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        let err = super::attach(io::Error::other(err), RequestId(7));
+        assert_eq!(format!("{:+}", err), "connection reset: request #7: timed out");
+        assert_eq!(
+            ::iter::messages(&err).collect::<Vec<_>>(),
+            ["connection reset", "request #7", "timed out"],
+        );
+
+        // the original, its sources, and the attachment are all found
+        assert!(::is::<io::Error>(&err));
+        let found = ::iter::all_of::<io::Error>(&err).map(|e| e.kind()).collect::<Vec<_>>();
+        assert_eq!(found, [io::ErrorKind::Other, io::ErrorKind::TimedOut]);
+        assert_eq!(::find::<RequestId>(&err).unwrap().0, 7);
+        // only known by name with `std`
+        if cfg!(feature = "std") {
+            assert_eq!(super::type_name(&err), "std::io::error::Error");
+        }
+
+        // the message is formatted with the rest of the chain
+        let (err, line) = (super::attach(::wrap("b", "a"), "meta"), line!());
+        assert_eq!(format!("{:+}", err), "b: meta: a");
+        assert!(format!("{:+#}", err).starts_with(&format!("b\n    at {}:{}", file!(), line)));
+        assert_eq!(super::location_of(&err).unwrap().to_string(), format!("{}:{}", file!(), line));
+
+        let err: ::BoxError = Box::new(err);
+        assert!(super::downcast::<RequestId>(err).is_err());
+    }
+
+    #[test]
+    fn wrap_opt() {
+        use core::error::Error;
+
+        let err = super::wrap_opt("b", Some("a"));
+        assert_eq!(err.source().unwrap().to_string(), "a");
+        assert_eq!(format!("{:+}", err), format!("{:+}", super::wrap("b", "a")));
+
+        let err = super::wrap_opt("b", None::<::BoxError>);
+        assert!(err.source().is_none());
+        assert_eq!(format!("{:+}", err), "b");
+    }
+
+    #[test]
+    fn wrap_all_only_errors() {
+        use core::error::Error;
+
+        let results = vec![Err("a"), Ok(1), Ok(2), Err("b")];
+        let (wrapped, line) = (
+            super::wrap_all(|i| format!("item {}", i), results).collect::<Vec<_>>(),
+            line!(),
+        );
+
+        let oks = wrapped.iter().filter_map(|r| r.as_ref().ok()).collect::<Vec<_>>();
+        assert_eq!(oks, [&1, &2]);
+
+        let errs = wrapped.iter().filter_map(|r| r.as_ref().err()).collect::<Vec<_>>();
+        assert_eq!(errs.len(), 2);
+        assert_eq!(format!("{:+}", errs[0]), "item 0: a");
+        assert_eq!(format!("{:+}", errs[1]), "item 3: b");
+        assert_eq!(errs[1].source().unwrap().to_string(), "b");
+
+        let frame = format!("\n    at {}:{}", file!(), line - 1);
+        assert!(format!("{:#}", errs[0]).contains(&frame));
+    }
+
+    #[test]
+    fn map_message() {
+        use core::error::Error;
+        use std::io;
+
+        let err = super::map_message(io::Error::other("disk on fire"), |msg| format!("read failed: {}", msg));
+        assert_eq!(err.to_string(), "read failed: disk on fire");
+        assert!(err.source().unwrap().is::<io::Error>());
+
+        // only the top message is given
+        let err = super::map_message(super::wrap("b", "a"), |msg| {
+            assert_eq!(msg, "b");
+            "c"
+        });
+        assert_eq!(format!("{:+}", err), "c: b: a");
+        assert_eq!(::iter::depth(&err), 3);
+    }
+
+    #[test]
+    fn wrap_ref_err() {
+        use core::error::Error;
+        use core::ptr;
+        use std::io;
+
+        let res: Result<(), io::Error> = Err(io::Error::other("disk on fire"));
+        let cause = res.as_ref().unwrap_err();
 
+        let (err, line) = (super::wrap_ref_err("read failed", cause), line!());
+        assert_eq!(err.to_string(), "read failed");
+        assert_eq!(format!("{:+}", err), "read failed: disk on fire");
+        assert!(ptr::addr_eq(err.source().unwrap(), cause));
 
-struct WrapperRef<'a, D> {
-    message: D,
-    cause: Option<&'a ErrorRef>,
-}
+        let frame = format!("read failed\n    at {}:{}", file!(), line);
+        assert!(format!("{:#}", err).starts_with(&frame));
+    }
 
-struct Opaque(BoxError);
+    #[test]
+    fn wrap_args_formats_in_scope() {
+        use core::error::Error;
 
-// ===== impl Wrapper =====
+        let id = String::from("abc");
+        let check = |err: &dyn Error| {
+            assert_eq!(err.to_string(), "request abc failed");
+            assert_eq!(err.source().unwrap().to_string(), "timed out");
+            assert_eq!(format!("{:+}", err), "request abc failed: timed out");
+            assert_eq!(format!("{:+}", ::fmt(err)), "request abc failed: timed out");
+        };
+        check(&super::wrap_args(format_args!("request {} failed", id), "timed out"));
 
-impl<D> Wrapper<D>
-where
-    D: fmt::Debug + fmt::Display + 'static,
-{
-    fn wrap_ref(&self) -> WrapperRef<&D> {
-        WrapperRef {
-            message: &self.message,
-            cause: self.source(),
-        }
+        let (err, line) = (super::wrap_args(format_args!("static"), "cause"), line!());
+        let frame = format!("static\n    at {}:{}", file!(), line);
+        assert!(format!("{:#}", err).starts_with(&frame));
     }
-}
 
-impl<D> fmt::Debug for Wrapper<D>
-where
-    D: fmt::Debug + fmt::Display + 'static,
-{
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&self.wrap_ref(), f)
+    #[test]
+    fn fields_in_chain_order() {
+        use std::io;
+
+        let err = super::with_field(io::Error::other("connection reset"), "attempt", 3);
+        let err = super::with_field(err, "host", "example.com");
+        let err = super::with_field(super::wrap_boxed("request failed", err), "request_id", "abc");
+
+        let fields = super::fields(&*err)
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["request_id=abc", "attempt=3", "host=example.com"]);
+
+        assert_eq!(err.to_string(), "request failed");
+        assert_eq!(format!("{:+}", ::fmt(&*err)), "request failed: connection reset");
+        assert!(super::fields(&super::new("no fields")).next().is_none());
     }
-}
 
-impl<D> fmt::Display for Wrapper<D>
-where
-    D: fmt::Debug + fmt::Display + 'static,
-{
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.wrap_ref(), f)
+    #[test]
+    fn fields_display_alternate() {
+        use std::io;
+
+        let err = super::with_field(io::Error::other("connection reset"), "host", "example.com");
+        assert_eq!(format!("{:#}", err), "connection reset\n    host=example.com");
+
+        let (err, line) = (super::with_field(super::wrap_boxed("request failed", err), "attempt", 3), line!());
+        assert_eq!(
+            format!("{:+#}", err),
+            format!(
+                "request failed{}\nCaused by: connection reset\n    host=example.com",
+                frame(&*err, line),
+            ),
+        );
     }
-}
 
-impl<D> Error for Wrapper<D>
-where
-    D: fmt::Debug + fmt::Display + 'static,
-{
-    fn source(&self) -> Option<&ErrorRef> {
-        self.cause.as_ref().map(|e| &**e as _)
+    #[test]
+    fn fields_keep_foreign_type() {
+        use std::io;
+
+        let err = super::with_field(io::Error::other("connection reset"), "host", "example.com");
+        assert!(::is::<io::Error>(&*err));
+        assert_eq!(::iter::find::<io::Error>(&*err).unwrap().to_string(), "connection reset");
+        assert_eq!(::iter::position::<io::Error>(&*err), Some(0));
+        assert_eq!(::iter::messages(&*err).collect::<Vec<_>>(), ["connection reset"]);
+
+        let err = super::wrap_boxed("request failed", err);
+        assert!(::is::<io::Error>(&*err));
+        assert_eq!(::iter::root_or::<io::Error>(&*err).to_string(), "connection reset");
+        let io = super::downcast::<io::Error>(err).unwrap();
+        assert_eq!(io.to_string(), "connection reset");
     }
-}
 
-// ===== impl WrapperRef =====
+    #[test]
+    fn from_messages_round_trips() {
+        let msgs = vec!["c".to_string(), "b".to_string(), "a".to_string()];
+        let err = super::from_messages(msgs.clone()).unwrap();
+        assert_eq!(::iter::messages(&err).collect::<Vec<_>>(), msgs);
+        assert_eq!(format!("{:+}", err), "c: b: a");
 
+        let err = super::from_messages(vec!["a".to_string()]).unwrap();
+        assert_eq!(::iter::messages(&err).collect::<Vec<_>>(), ["a"]);
 
-impl<'a, D> WrapperRef<'a, D>
-where
-    D: fmt::Debug + fmt::Display,
-{
-    fn joiner(&self, f: &fmt::Formatter) -> &'static str {
-        if f.alternate() {
-            "\nCaused by: "
-        } else {
-            ": "
-        }
+        assert!(super::from_messages(Vec::new()).is_none());
     }
 
-    fn fmt_all_sources(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let joiner = self.joiner(f);
-        for err in ::iter::sources(self) {
-            f.write_str(joiner)?;
+    #[test]
+    fn clone_chain_formats_same() {
+        use std::io;
 
-            // Propagate if chain ends in `Opaque`
-            if err.is::<Opaque>() {
-                return if f.alternate() {
-                    write!(f, "{:+#}", err)
-                } else {
-                    write!(f, "{:+}", err)
-                };
-            }
+        let err = super::wrap("c", super::wrap("b", io::Error::other("a")));
+        let copy = super::clone_chain(&err);
 
-            // else
-            write!(f, "{:-}", err)?;
+        assert_eq!(format!("{}", copy), format!("{}", err));
+        assert_eq!(format!("{:+}", copy), format!("{:+}", err));
+        assert_eq!(format!("{:+.1}", copy), format!("{:+.1}", err));
+        // backtraces aren't copied
+        if !cfg!(feature = "backtrace") {
+            assert_eq!(format!("{:+#}", copy), format!("{:+#}", err));
         }
 
-        Ok(())
+        // nor hidden by an opaque
+        let err = super::wrap("d", super::opaque(super::wrap("c", super::wrap("b", io::Error::other("a")))));
+        let copy = super::clone_chain(&err);
+
+        assert_eq!(format!("{}", copy), format!("{}", err));
+        assert_eq!(format!("{:+}", copy), format!("{:+}", err));
+        assert_eq!(format!("{:+.1}", copy), format!("{:+.1}", err));
+        if !cfg!(feature = "backtrace") {
+            assert_eq!(format!("{:+#}", copy), format!("{:+#}", err));
+        }
+        let copy = super::clone_chain(&super::opaque(super::wrap("b", "a")));
+        assert_eq!(format!("{}", copy), "b");
+        assert_eq!(format!("{:+}", copy), "b: a");
     }
 
-    fn fmt_max_sources(&self, f: &mut fmt::Formatter, mut max: usize) -> fmt::Result {
-        let joiner = self.joiner(f);
-        let mut sources = ::iter::sources(self);
-        loop {
-            if max == 0 {
-                return Ok(());
-            }
-            max -= 1;
+    #[test]
+    fn clone_chain_outlives_original() {
+        let copy = {
+            let err = super::wrap("b", super::opaque(super::wrap("hidden", "a")));
+            super::clone_chain(&err)
+        };
+        let cloned = copy.clone();
+        drop(copy);
 
-            let err = match sources.next() {
-                Some(err) => err,
-                None => break,
-            };
+        assert_eq!(format!("{:+}", cloned), "b: hidden: a");
+        assert_eq!(::iter::depth(&cloned), 3);
+    }
 
-            f.write_str(joiner)?;
+    // aggregate()
 
-            // Propagate if chain ends in `Opaque`
-            if err.is::<Opaque>() {
-                return if f.alternate() {
-                    write!(f, "{:+#.*}", max, err)
-                } else {
-                    write!(f, "{:+.*}", max, err)
-                };
-            }
+    #[test]
+    fn aggregate_zero() {
+        use core::error::Error;
 
-            //else
-            write!(f, "{:-}", err)?;
+        let causes: Vec<::BoxError> = Vec::new();
+        let (err, line) = (super::aggregate("failed", causes), line!());
 
-        }
+        assert!(err.source().is_none());
+        assert_eq!(format!("{}", err), "failed");
+        assert_eq!(format!("{:+}", err), "failed");
+        assert_eq!(format!("{:+#}", err), format!("failed\n    at {}:{}", file!(), line));
+    }
 
-        Ok(())
+    #[test]
+    fn aggregate_one() {
+        use core::error::Error;
+
+        let (err, line) = (super::aggregate("failed", vec!["a"]), line!());
+
+        assert_eq!(err.source().unwrap().to_string(), "a");
+        assert_eq!(format!("{:+}", err), "failed: a");
+        assert_eq!(
+            format!("{:+#}", err),
+            format!("failed\n    at {}:{}\nCaused by:\n    a", file!(), line)
+        );
     }
-}
 
-impl<'a, D: fmt::Debug> fmt::Debug for WrapperRef<'a, D> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if let Some(ref cause) = self.cause {
-            f.debug_tuple("")
-                .field(&self.message)
-                .field(cause)
-                .finish()
-        } else {
-            fmt::Debug::fmt(&self.message, f)
-        }
+    #[test]
+    fn aggregate_three() {
+        use core::error::Error;
+
+        let causes = vec![
+            super::new_boxed("a"),
+            super::wrap_boxed("b", "b2"),
+            super::new_boxed("c"),
+        ];
+        let (err, line) = (super::aggregate("failed", causes), line!());
+
+        assert_eq!(err.source().unwrap().to_string(), "a");
+        assert_eq!(format!("{}", err), "failed");
+        assert_eq!(format!("{:+}", err), "failed: a");
+        assert_eq!(
+            format!("{:+#}", err),
+            format!(
+                "failed\n    at {}:{}\nCaused by:\n    a\n    b: b2\n    c",
+                file!(),
+                line,
+            )
+        );
+        assert_eq!(
+            format!("{:+#.1}", err),
+            format!(
+                "failed\n    at {}:{}\nCaused by:\n    a\n    b\n    c",
+                file!(),
+                line,
+            )
+        );
+
+
+        // the width indents the label and the causes, like the sources
+        assert_eq!(
+            format!("{:+#2}", err),
+            format!(
+                "failed\n    at {}:{}\n  Caused by:\n    a\n    b: b2\n    c",
+                file!(),
+                line,
+            )
+        );
     }
-}
 
-impl<'a, D> fmt::Display for WrapperRef<'a, D>
-where
-    D: fmt::Debug + fmt::Display,
-{
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // {:+} means print the chain
-        if f.sign_plus() {
-            // first message with no flags...
-            write!(f, "{:-}", self.message)?;
-            // precision flag signals max source chain iteration...
-            if let Some(max) = f.precision() {
-                self.fmt_max_sources(f, max)
-            } else {
-                self.fmt_all_sources(f)
-            }
-        } else {
-            // reset all formatter flags
-            write!(f, "{}", self.message)
-        }
+    // downcast()
+
+    #[test]
+    fn downcast_recovers_type() {
+        use std::io;
+
+        let err = super::wrap_boxed("c", super::wrap("b", io::Error::other("a")));
+        let io = super::downcast::<io::Error>(err).unwrap();
+        assert_eq!(io.to_string(), "a");
+
+        let err = super::new_boxed("a");
+        assert!(super::downcast::<io::Error>(err).is_err());
     }
-}
 
-impl<'a, D> Error for WrapperRef<'a, D>
-where
-    D: fmt::Debug + fmt::Display,
-{
-    fn source(&self) -> Option<&ErrorRef> {
-        self.cause.as_ref().map(|e| *e)
+    #[test]
+    fn downcast_failure_returns_original() {
+        use std::io;
+
+        // not found
+        let err = super::wrap_boxed("c", super::wrap("b", "a"));
+        let err = super::downcast::<io::Error>(err).unwrap_err();
+        assert_eq!(format!("{:+}", err), "c: b: a");
+
+        // only reachable through a foreign error
+        let foreign = io::Error::other(super::wrap("b", fmt::Error));
+        let err = super::wrap_boxed("c", foreign);
+        assert!(::is::<fmt::Error>(&*err));
+        let err = super::downcast::<fmt::Error>(err).unwrap_err();
+        assert_eq!(format!("{:+}", err), "c: b: an error occurred when formatting an argument");
     }
-}
 
-// ===== impl Opaque =====
+    #[test]
+    fn forwarded_description_is_not_a_wrapper() {
+        use core::error::Error;
+        use std::io;
 
-impl Opaque {
-    fn wrap_ref(&self) -> WrapperRef<&ErrorRef> {
-        WrapperRef {
-            message: &*self.0,
-            cause: self.0.source(),
+        // A foreign error that forwards `description()` to an error of this
+        // crate must not be mistaken for one.
+        #[derive(Debug)]
+        struct Forward(::BoxError);
+
+        impl fmt::Display for Forward {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("forward")
+            }
         }
-    }
-}
 
-impl fmt::Debug for Opaque {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&self.0, f)
-    }
-}
+        impl Error for Forward {
+            #[allow(deprecated)]
+            fn description(&self) -> &str {
+                self.0.description()
+            }
 
-impl fmt::Display for Opaque {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.wrap_ref(), f)
+            fn source(&self) -> Option<&::ErrorRef> {
+                Some(&*self.0)
+            }
+        }
+
+        let err = Forward(super::wrap_boxed("b", io::Error::other("a")));
+        assert_eq!(super::type_name(&err), "unknown");
+        assert!(!super::follows_format(&err));
+        assert_eq!(format!("{:+}", ::fmt(&err)), "forward: b: a");
+
+        let err: ::BoxError = Box::new(err);
+        let err = super::downcast::<io::Error>(err).unwrap_err();
+        assert_eq!(format!("{:+}", ::fmt(&*err)), "forward: b: a");
+
+        let err = super::with_field(err, "k", 1);
+        assert!(err.is::<super::WithFields>());
+        let fields = super::fields(&*err).map(|(k, _)| k).collect::<Vec<_>>();
+        assert_eq!(fields, ["k"]);
     }
-}
 
-// No source chains for opaque errors!
-impl Error for Opaque {}
+    // opaque()
 
-#[cfg(test)]
-mod tests {
     #[test]
-    fn display_default() {
-        let cause = "cat hair in generator";
-        let top = "ship exploded";
+    fn opaque_has_no_sources() {
+        use core::error::Error;
 
-        let op = super::new(cause);
-        assert_eq!(format!("{}", op), cause);
+        let w = super::wrap("b", "a");
+        assert!(w.source().is_some());
 
-        let wp = super::wrap(top, cause);
-        assert_eq!(format!("{}", wp), top);
+        let op = super::opaque(w);
+        assert!(op.source().is_none());
+    }
 
-        let wp_op = super::wrap(top, op);
-        assert_eq!(format!("{}", wp_op), top);
+    #[test]
+    fn opaque_is_detectable() {
+        let w = super::wrap("b", "a");
+        assert!(!::is::<super::Opaque>(&w));
+
+        let e = super::wrap("c", super::opaque(w));
+        assert!(::is::<super::Opaque>(&e));
+        assert_eq!(::find::<super::Opaque>(&e).unwrap().to_string(), "b");
     }
 
     #[test]
-    fn display_chain() {
-        let cause = "cat hair in generator";
-        let top = "ship exploded";
+    fn opaque_hides_types() {
+        use std::io;
 
-        let op = super::new(cause);
-        assert_eq!(format!("{:+}", op), cause);
+        let w = super::wrap("b", io::Error::other("a"));
+        assert!(::is::<io::Error>(&w));
+        assert!(has_message::<&str>(&w));
 
-        let wp = super::wrap(top, cause);
-        assert_eq!(format!("{:+}", wp), format!("{}: {}", top, cause));
+        let op = super::opaque(w);
+        assert_eq!(::iter::chain(&op).count(), 1);
+        assert!(::is::<super::Opaque>(&op));
+        assert!(::find::<super::Opaque>(&op).is_some());
+        assert!(!::is::<io::Error>(&op));
+        assert!(!has_message::<&str>(&op));
+        assert!(::find::<io::Error>(&op).is_none());
+        assert!(::iter::find_last::<io::Error>(&op).is_none());
+        assert_eq!(::iter::root(&op).to_string(), "b");
 
-        let wp_op = super::wrap(top, op);
-        assert_eq!(format!("{:+}", wp_op), format!("{}: {}", top, cause));
+        // neither after wrapping it again, nor once typed
+        let e = super::wrap("c", op);
+        assert_eq!(::iter::chain(&e).count(), 2);
+        assert!(!::is::<io::Error>(&e));
+        assert!(::find::<super::Opaque>(&e).is_some());
+
+        let e = super::opaque_typed(super::wrap("b", io::Error::other("a")));
+        assert!(!::is::<io::Error>(&*e));
+        assert!(!has_message::<&str>(&*e));
+
+        // even though it all still prints
+        assert_eq!(format!("{:+}", e), "b: a");
+
+        // only on purpose
+        assert!(::iter::chain_through_opaque(&*e).any(|e| e.is::<io::Error>()));
     }
 
     #[test]
-    fn display_chain_when_message_is_wrapped() {
+    fn type_name() {
+        use std::io;
 
-        let msg = super::wrap("b", "a");
-        let err = super::wrap(msg, "z");
+        assert_eq!(super::type_name(&super::new("a")), "&str");
+        assert_eq!(super::type_name(&super::wrap(String::from("b"), "a")), "alloc::string::String");
+        assert_eq!(super::type_name(&super::opaque(super::new("a"))), "&str");
 
-        assert_eq!(format!("{:+}", err), "b: z");
+        // not the private types holding the message
+        assert_eq!(super::type_name(&super::new_static("a")), "&str");
+        let err = super::categorized(io::ErrorKind::TimedOut, "a");
+        assert_eq!(super::type_name(&err), core::any::type_name::<io::ErrorKind>());
+        let f = |f: &mut fmt::Formatter| f.write_str("a");
+        assert_eq!(super::type_name(&super::lazy(f)), core::any::type_name_of_val(&f));
+
+        // only known by name with `std`
+        if cfg!(feature = "std") {
+            let err = io::Error::other("a");
+            assert_eq!(super::type_name(&err), core::any::type_name::<io::Error>());
+            let err = super::with_field(err, "k", 1);
+            assert_eq!(super::type_name(&*err), core::any::type_name::<io::Error>());
+        }
+
+        let err: ::BoxError = "a".into();
+        assert_eq!(super::type_name(&*err), "unknown");
     }
 
     #[test]
-    fn display_alternative() {
-        let cause = "cat hair in generator";
-        let top = "ship exploded";
+    fn opaque_keep_root() {
+        use core::error::Error;
+        use std::io;
 
-        let op = super::new(cause);
-        assert_eq!(format!("{:#}", op), cause);
-        assert_eq!(format!("{:+#}", op), cause);
+        #[derive(Debug)]
+        struct Layer;
+
+        impl fmt::Display for Layer {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("layer")
+            }
+        }
 
-        let alt = format!("{}\nCaused by: {}", top, cause);
+        let inner = super::wrap(Layer, io::Error::other("timed out"));
+        assert!(has_message::<Layer>(&inner));
 
-        let wp = super::wrap(top, cause);
-        assert_eq!(format!("{:+#}", wp), alt);
+        let err = super::wrap("request failed", super::opaque_keep_root(inner));
+        assert!(::is::<io::Error>(&err));
+        assert!(!has_message::<Layer>(&err));
+        assert_eq!(::iter::depth(&err), 3);
+        assert_eq!(::iter::root(&err).to_string(), "timed out");
+        assert!(super::is_opaque(err.source().unwrap()));
 
-        let wp_op = super::wrap(top, op);
-        assert_eq!(format!("{:+#}", wp_op), alt);
+        // the root is only printed once
+        assert_eq!(format!("{:+}", err), "request failed: layer: timed out");
+        assert_eq!(
+            ::fmt::reversed(&err).to_string(),
+            "layer: timed out <- request failed"
+        );
+
+        // nor is a hidden error without a source its own root
+        let err = super::opaque_keep_root(super::new("only"));
+        assert!(err.source().is_none());
+        assert_eq!(::iter::messages(&err).collect::<Vec<_>>(), ["only"]);
+        assert_eq!(format!("{:+}", err), "only");
     }
 
     #[test]
-    fn display_chain_max() {
-        let a = "a";
-        let op = super::new(a);
-        assert_eq!(format!("{:.0}", op), a);
-        assert_eq!(format!("{:.1}", op), a);
-        assert_eq!(format!("{:+.0}", op), a);
-        assert_eq!(format!("{:+.1}", op), a);
+    fn is_opaque() {
+        use core::error::Error;
 
-        let wp = super::wrap("b", "a");
-        assert_eq!(format!("{:.0}", wp), "b");
-        assert_eq!(format!("{:.1}", wp), "b");
-        assert_eq!(format!("{:+.0}", wp), "b");
-        assert_eq!(format!("{:+.1}", wp), "b: a");
+        let err = super::opaque(super::wrap("b", "a"));
+        assert!(super::is_opaque(&err));
+        assert!(!super::is_opaque(&super::wrap("b", "a")));
 
-        let wp2 = super::wrap("c", wp);
-        assert_eq!(format!("{:.0}", wp2), "c");
-        assert_eq!(format!("{:.1}", wp2), "c");
-        assert_eq!(format!("{:+.0}", wp2), "c");
-        assert_eq!(format!("{:+.1}", wp2), "c: b");
-        assert_eq!(format!("{:+.2}", wp2), "c: b: a");
+        let err = super::wrap("c", err);
+        assert!(!super::is_opaque(&err));
+        assert!(super::is_opaque(err.source().unwrap()));
     }
 
-    // opaque()
+    #[test]
+    fn split_opaque() {
+        let err = super::wrap("c", super::wrap("b", "a"));
+        let (visible, hidden) = super::split_opaque(&err);
+        assert_eq!(visible.iter().map(|e| e.to_string()).collect::<Vec<_>>(), ["c", "b", "a"]);
+        assert!(hidden.is_none());
+
+        let err = super::wrap("d", super::opaque(super::wrap("c", super::wrap("b", "a"))));
+        let (visible, hidden) = super::split_opaque(&err);
+        assert_eq!(visible.len(), 2);
+        assert!(super::is_opaque(visible[1]));
+        let hidden = hidden.unwrap();
+        assert_eq!(::iter::messages(hidden).collect::<Vec<_>>(), ["c", "b", "a"]);
+    }
 
     #[test]
-    fn opaque_has_no_sources() {
-        use crate::Error;
+    fn opaque_if_only_when_pred() {
+        use std::io;
 
-        let w = super::wrap("b", "a");
-        assert!(w.source().is_some());
+        let timeout = || super::wrap("request failed", io::Error::from(io::ErrorKind::TimedOut));
 
-        let op = super::opaque(w);
-        assert!(op.source().is_none());
+        let err = super::opaque_if(timeout(), ::is::<io::Error>);
+        assert!(err.source().is_none());
+        assert!(err.is::<super::Opaque>());
+        assert_eq!(format!("{:+}", err), format!("{:+}", timeout()));
+
+        let err = super::opaque_if(timeout(), ::is::<fmt::Error>);
+        assert_eq!(err.source().unwrap().to_string(), "timed out");
+        assert!(::is::<io::Error>(&*err));
+    }
+
+    #[test]
+    fn opaque_tagged_only_exposes_tag() {
+        use std::io;
+
+        #[derive(Debug, PartialEq)]
+        struct Tag(u8);
+
+        let w = super::wrap("b", io::Error::other("a"));
+        assert!(::is::<io::Error>(&w));
+
+        let e = super::wrap("c", super::opaque_tagged(w, Tag(3)));
+        assert!(!::is::<io::Error>(&e));
+        assert!(::find::<io::Error>(&e).is_none());
+        assert_eq!(super::opaque_tag::<Tag>(&e), Some(&Tag(3)));
+        assert_eq!(super::opaque_tag::<u8>(&e), None);
+        assert_eq!(format!("{:+}", e), "c: b: a");
+
+        let e = super::opaque(super::wrap("b", "a"));
+        assert_eq!(super::opaque_tag::<Tag>(&e), None);
     }
 
     #[test]
@@ -394,4 +3355,54 @@ mod tests {
         assert_eq!(format!("{:+}", e), "d: c: b: a");
         assert_eq!(format!("{:+.1}", e), "d: c");
     }
+
+    #[test]
+    fn opaque_display_top_message() {
+        let e = super::opaque(super::wrap("b", "a"));
+        assert_eq!(format!("{}", e), "b");
+        assert_eq!(format!("{:.0}", e), "b");
+        assert_eq!(format!("{:+}", e), "b: a");
+
+        // the hidden error prints its chain in any form
+        let e = super::opaque(::Source::new(super::wrap("b", "a")));
+        assert_eq!(format!("{}", e), "b: a");
+        assert_eq!(format!("{:.0}", e), "b: a");
+        assert_eq!(format!("{:+}", e), "b: a");
+        assert_eq!(format!("{:+}", super::wrap("c", e)), "c: b: a");
+    }
+
+    #[test]
+    fn opaque_chain_max() {
+        // opaque at the top
+        let e = super::opaque(super::wrap("b", "a"));
+        assert_eq!(format!("{:+.0}", e), "b");
+        assert_eq!(format!("{:+.1}", e), "b: a");
+        assert_eq!(format!("{:+.2}", e), "b: a");
+
+        // opaque as the first source
+        let e = super::wrap("c", super::opaque(super::wrap("b", "a")));
+        assert_eq!(format!("{:+.0}", e), "c");
+        assert_eq!(format!("{:+.1}", e), "c: b");
+        assert_eq!(format!("{:+.2}", e), "c: b: a");
+        assert_eq!(format!("{:+.3}", e), "c: b: a");
+
+        // opaque as the second source
+        let e = super::wrap("d", super::wrap("c", super::opaque(super::wrap("b", "a"))));
+        assert_eq!(format!("{:+.0}", e), "d");
+        assert_eq!(format!("{:+.1}", e), "d: c");
+        assert_eq!(format!("{:+.2}", e), "d: c: b");
+        assert_eq!(format!("{:+.3}", e), "d: c: b: a");
+
+        // opaque with nothing hidden, ending the chain
+        let e = super::wrap("b", super::opaque("a"));
+        assert_eq!(format!("{:+.0}", e), "b");
+        assert_eq!(format!("{:+.1}", e), "b: a");
+        assert_eq!(format!("{:+.2}", e), "b: a");
+
+        // nested opaques
+        let e = super::wrap("c", super::opaque(super::wrap("b", super::opaque("a"))));
+        assert_eq!(format!("{:+.0}", e), "c");
+        assert_eq!(format!("{:+.1}", e), "c: b");
+        assert_eq!(format!("{:+.2}", e), "c: b: a");
+    }
 }
@@ -0,0 +1,113 @@
+use core::error::Error;
+use super::BoxError;
+
+/// Call `f` until it succeeds, up to `attempts` times.
+///
+/// If every attempt fails, the last error is made [opaque](::opaque) and
+/// wrapped with a `"too many attempts"` message. This way, the returned error
+/// still prints what went wrong, but no longer matches whatever made it worth
+/// retrying in the first place.
+///
+/// `f` is always called at least once.
+///
+/// # Example
+///
+/// ```
+/// use std::cell::Cell;
+///
+/// let calls = Cell::new(0);
+/// let res = errors::retry(3, || {
+///     calls.set(calls.get() + 1);
+///     Err::<(), _>("timed out")
+/// });
+///
+/// let err = res.unwrap_err();
+/// assert_eq!(calls.get(), 3);
+/// assert_eq!(format!("{:+}", err), "too many attempts: timed out");
+/// ```
+// With every feature enabled, the frame of an error is larger than clippy
+// likes in a `Result`, but it is the same size as what `errors::wrap` returns.
+#[allow(clippy::result_large_err)]
+#[track_caller]
+pub fn retry<T, E, F>(attempts: usize, mut f: F) -> Result<T, impl Error>
+where
+    E: Into<BoxError>,
+    F: FnMut() -> Result<T, E>,
+{
+    let mut remaining = attempts;
+    loop {
+        match f() {
+            Ok(t) => return Ok(t),
+            Err(e) => {
+                if remaining <= 1 {
+                    return Err(exhausted(e.into()));
+                }
+                remaining -= 1;
+            }
+        }
+    }
+}
+
+// Not generic, so the returned error doesn't depend on the types of `retry`.
+#[track_caller]
+fn exhausted(err: BoxError) -> impl Error {
+    ::new::wrap("too many attempts", ::new::opaque(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io;
+
+    use core::error::Error;
+
+    #[test]
+    fn retry_stops_after_attempts() {
+        let calls = Cell::new(0);
+        let res = super::retry(3, || {
+            calls.set(calls.get() + 1);
+            Err::<(), _>("timed out")
+        });
+
+        assert!(res.is_err());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn retry_error_is_opaque() {
+        let res = super::retry(3, || Err::<(), _>(io::Error::other("timed out")));
+
+        let err = res.unwrap_err();
+        assert_eq!(format!("{:+}", err), "too many attempts: timed out");
+        assert!(err.source().unwrap().source().is_none());
+        assert!(!::is::<io::Error>(&err));
+    }
+
+    #[test]
+    fn retry_returns_first_success() {
+        let calls = Cell::new(0);
+        let res = super::retry(5, || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 2 {
+                Err("timed out")
+            } else {
+                Ok(calls.get())
+            }
+        });
+
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn retry_zero_attempts_calls_once() {
+        let calls = Cell::new(0);
+        let res = super::retry(0, || {
+            calls.set(calls.get() + 1);
+            Err::<(), _>("timed out")
+        });
+
+        assert!(res.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
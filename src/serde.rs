@@ -0,0 +1,152 @@
+//! Serializing `Error`s with `serde`.
+//!
+//! *Requires the `serde` feature.*
+
+use core::fmt;
+
+use serde_crate::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
+
+use core::error::Error;
+
+/// A wrapper to serialize an error and its source chain.
+///
+/// The error is serialized as a struct with its `message`, and a `causes`
+/// array containing the `message` of each error in its source chain. Since
+/// this follows `iter::sources`, the chain ends at an
+/// [`Opaque`](::Opaque) barrier.
+///
+/// # Example
+///
+/// ```
+/// # extern crate errors;
+/// # extern crate serde_json;
+/// # fn main() {
+/// let err = errors::wrap("exploded", "cat hair in generator");
+///
+/// let json = serde_json::to_string(&errors::serde::Serializable(&err)).unwrap();
+///
+/// assert_eq!(
+///     json,
+///     r#"{"message":"exploded","causes":[{"message":"cat hair in generator"}]}"#
+/// );
+/// # }
+/// ```
+pub struct Serializable<'a>(pub &'a dyn Error);
+
+struct Message<'a>(&'a dyn Error);
+
+struct Causes<'a>(&'a dyn Error);
+
+struct Cause<'a>(&'a dyn Error);
+
+impl<'a> Serialize for Serializable<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Error", 2)?;
+        s.serialize_field("message", &Message(self.0))?;
+        s.serialize_field("causes", &Causes(self.0))?;
+        s.end()
+    }
+}
+
+impl<'a> Serialize for Message<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&Plain(self.0))
+    }
+}
+
+impl<'a> Serialize for Causes<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        for err in ::iter::sources(self.0).printed() {
+            seq.serialize_element(&Cause(err))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'a> Serialize for Cause<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Cause", 1)?;
+        s.serialize_field("message", &Message(self.0))?;
+        s.end()
+    }
+}
+
+/// Only the message of an error, without any formatter flags besides `-`,
+/// which asks it for only its own message.
+struct Plain<'a>(&'a dyn Error);
+
+impl<'a> fmt::Display for Plain<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:-}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{self, json};
+
+    use super::Serializable;
+
+    #[test]
+    fn serialize_chain() {
+        let err = ::wrap("b", "a");
+        let value = serde_json::to_value(Serializable(&err)).unwrap();
+
+        assert_eq!(value, json!({
+            "message": "b",
+            "causes": [
+                { "message": "a" },
+            ],
+        }));
+    }
+
+    #[test]
+    fn serialize_stops_at_opaque() {
+        let err = ::wrap("c", ::opaque(::wrap("b", "a")));
+        let value = serde_json::to_value(Serializable(&err)).unwrap();
+
+        assert_eq!(value, json!({
+            "message": "c",
+            "causes": [
+                { "message": "b" },
+            ],
+        }));
+    }
+
+    #[test]
+    fn serialize_only_own_message() {
+        use core::error::Error;
+        use core::fmt;
+
+        // Prints its source in `{}`, unless only asked for its own message.
+        #[derive(Debug)]
+        struct Joined(::BoxError);
+
+        impl fmt::Display for Joined {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                if f.sign_minus() {
+                    f.write_str("b")
+                } else {
+                    write!(f, "b: {}", self.0)
+                }
+            }
+        }
+
+        impl Error for Joined {
+            fn source(&self) -> Option<&::ErrorRef> {
+                Some(&*self.0)
+            }
+        }
+
+        let err = Joined("a".into());
+        let value = serde_json::to_value(Serializable(&err)).unwrap();
+
+        assert_eq!(value, json!({
+            "message": "b",
+            "causes": [
+                { "message": "a" },
+            ],
+        }));
+    }
+}
@@ -0,0 +1,199 @@
+//! Adding context to the errors of `tower` services.
+//!
+//! *Requires the `tower` feature.*
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context as TaskContext, Poll};
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use super::BoxError;
+
+/// A `Layer` that wraps the errors of a service with a message made from
+/// the request.
+///
+/// On `Err`, the service returns `errors::wrap(f(&req), err)`, boxed, so it
+/// stacks with any other middleware expecting errors that are
+/// `Into<BoxError>`. Since the request is moved into the inner service, the
+/// message is made before calling it, even if the call then succeeds.
+///
+/// # Example
+///
+/// ```
+/// let layer = errors::tower::ContextLayer::new(|path: &String| {
+///     format!("failed to fetch {}", path)
+/// });
+/// # let _ = layer;
+/// ```
+#[derive(Clone, Debug)]
+pub struct ContextLayer<F> {
+    f: F,
+}
+
+/// A service that wraps the errors of an inner service, created by a
+/// [`ContextLayer`].
+#[derive(Clone, Debug)]
+pub struct Context<S, F> {
+    inner: S,
+    f: F,
+}
+
+/// The `Future` returned by a [`Context`] service.
+#[derive(Debug)]
+pub struct ContextFuture<Fut, D> {
+    future: Fut,
+    message: Option<D>,
+}
+
+// ===== impl ContextLayer =====
+
+impl<F> ContextLayer<F> {
+    /// Create a layer that calls `f` with each request to make the message
+    /// of an error.
+    pub fn new(f: F) -> Self {
+        ContextLayer { f }
+    }
+}
+
+impl<S, F: Clone> Layer<S> for ContextLayer<F> {
+    type Service = Context<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Context {
+            inner,
+            f: self.f.clone(),
+        }
+    }
+}
+
+// ===== impl Context =====
+
+impl<S, F> Context<S, F> {
+    /// Get a reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consume this, returning the inner service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, F, Req, D> Service<Req> for Context<S, F>
+where
+    S: Service<Req>,
+    S::Error: Into<BoxError>,
+    F: Fn(&Req) -> D,
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = ContextFuture<S::Future, D>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let message = (self.f)(&req);
+        ContextFuture {
+            future: self.inner.call(req),
+            message: Some(message),
+        }
+    }
+}
+
+// ===== impl ContextFuture =====
+
+impl<Fut, T, E, D> Future for ContextFuture<Fut, D>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<BoxError>,
+    D: fmt::Debug + fmt::Display + Send + Sync + 'static,
+{
+    type Output = Result<T, BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        // Safety: `future` is pinned along with `self`, and never moved out.
+        // `message` is never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        match future.poll(cx) {
+            Poll::Ready(Ok(t)) => Poll::Ready(Ok(t)),
+            Poll::Ready(Err(e)) => {
+                let message = this
+                    .message
+                    .take()
+                    .expect("ContextFuture polled after completion");
+                Poll::Ready(Err(::new::wrap_boxed(message, e)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::{self, Future, Ready};
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    use super::ContextLayer;
+
+    // Succeeds for `"ok"`, and fails for anything else.
+    #[derive(Clone)]
+    struct Mock;
+
+    impl Service<&'static str> for Mock {
+        type Response = &'static str;
+        type Error = &'static str;
+        type Future = Ready<Result<&'static str, &'static str>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            if req == "ok" {
+                future::ready(Ok("done"))
+            } else {
+                future::ready(Err("cat hair in generator"))
+            }
+        }
+    }
+
+    fn call<S: Service<&'static str>>(svc: &mut S, req: &'static str) -> Result<S::Response, S::Error> {
+        let mut cx = Context::from_waker(Waker::noop());
+        match svc.poll_ready(&mut cx) {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(e)) => return Err(e),
+            Poll::Pending => panic!("mock is always ready"),
+        }
+        let mut fut = Box::pin(svc.call(req));
+        match Pin::new(&mut fut).as_mut().poll(&mut cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => panic!("mock is always ready"),
+        }
+    }
+
+    #[test]
+    fn wraps_err_with_request_context() {
+        let mut svc = ContextLayer::new(|req: &&str| format!("request {:?} failed", req)).layer(Mock);
+
+        assert_eq!(call(&mut svc, "ok").unwrap(), "done");
+
+        let err = call(&mut svc, "launch").unwrap_err();
+        assert_eq!(err.to_string(), "request \"launch\" failed");
+        assert_eq!(
+            format!("{:+}", err),
+            "request \"launch\" failed: cat hair in generator"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+#![cfg(not(feature = "backtrace"))]
+
+extern crate errors;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Counts every allocation of the test binary, so this lives in its own test
+// binary, with a single test, to not count those of other tests.
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::SeqCst);
+        LIVE.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+// How many allocations `f` makes, and how many bytes it leaves allocated.
+fn measure<T, F: FnOnce() -> T>(f: F) -> (usize, isize, T) {
+    let (allocs, live) = (ALLOCS.load(Ordering::SeqCst), LIVE.load(Ordering::SeqCst));
+    let t = f();
+    (
+        ALLOCS.load(Ordering::SeqCst) - allocs,
+        LIVE.load(Ordering::SeqCst) as isize - live as isize,
+        t,
+    )
+}
+
+#[test]
+fn allocations() {
+    // anything set up lazily on first use isn't counted
+    drop(errors::new("warm up"));
+
+    // the message is boxed, even a `&'static str`
+    let (allocs, _, err) = measure(|| errors::new("ship exploded"));
+    assert_eq!(allocs, 1);
+    drop(err);
+
+    // and a format string message is boxed with its arguments, but not
+    // formatted until the error is
+    let name = "Nostromo";
+    let (allocs, _, err) = measure(|| errors::new!("ship {} exploded", name));
+    assert_eq!(allocs, 1);
+    drop(err);
+
+    // `errors::msg` drops the extra capacity of the `String`, which
+    // `errors::new_boxed` keeps
+    let message = || {
+        let mut s = String::with_capacity(1024);
+        s.push_str("ship exploded");
+        s
+    };
+    let (s1, s2) = (message(), message());
+    let (allocs, boxed_live, boxed) = measure(|| errors::new_boxed(s1));
+    assert_eq!(allocs, 2);
+    // one more, to shrink the `String`
+    let (allocs, msg_live, msg) = measure(move || errors::msg(s2));
+    assert_eq!(allocs, 3);
+    assert!(msg_live + 1000 < boxed_live, "{} vs {}", msg_live, boxed_live);
+    assert_eq!(msg.to_string(), boxed.to_string());
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "backtrace")]
+
+extern crate errors;
+
+use std::env;
+
+// Backtrace capturing is decided from the environment only once, so this
+// lives in its own test binary to force it on.
+#[test]
+fn captures_backtrace() {
+    env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let err = errors::wrap("ship exploded", "cat hair in generator");
+
+    let bt = errors::backtrace(&err).expect("backtrace captured");
+    let bt = bt.to_string();
+    assert!(bt.contains("captures_backtrace"), "{}", bt);
+
+    let alt = format!("{:#}", err);
+    assert!(alt.starts_with("ship exploded\n    at "), "{}", alt);
+    assert!(alt.ends_with(&bt), "{}", alt);
+    assert!(!format!("{}", err).contains(&bt));
+
+    // foreign errors have no backtrace
+    let io = std::io::Error::other("boom");
+    assert!(errors::backtrace(&io).is_none());
+}
+
+#[test]
+fn main_prints_deepest_backtrace() {
+    env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let root = errors::new("cat hair in generator");
+    let bt = errors::backtrace(&root).expect("backtrace captured").to_string();
+    assert!(bt.contains("main_prints_deepest_backtrace"), "{}", bt);
+
+    let main = errors::Main::from(std::io::Error::other(errors::wrap("ship exploded", root)));
+    let out = format!("{:?}", main);
+    assert!(out.contains("\nCaused by: cat hair in generator"), "{}", out);
+    assert!(out.ends_with(&bt), "{}", out);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "log")]
+
+extern crate errors;
+extern crate log;
+
+use std::sync::Mutex;
+
+use log::{Level, Log, Metadata, Record};
+
+// Records the level and message of every log record.
+struct Recorder {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static RECORDER: Recorder = Recorder {
+    records: Mutex::new(Vec::new()),
+};
+
+// The logger is global, so everything is checked in a single test.
+#[test]
+fn logs_chain() {
+    log::set_logger(&RECORDER).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let take = || std::mem::take(&mut *RECORDER.records.lock().unwrap());
+
+    let err = errors::wrap("ship exploded", "cat hair in generator");
+    errors::log_error(&err);
+    assert_eq!(
+        take(),
+        [(Level::Error, "ship exploded: cat hair in generator".to_string())]
+    );
+
+    // foreign errors too
+    let err = std::io::Error::other(errors::wrap("b", "a"));
+    errors::log_error(&err);
+    assert_eq!(take(), [(Level::Error, "b: a".to_string())]);
+
+    // only a logged `Main`, once its `Debug` is printed
+    let main = errors::Main::from(errors::wrap("b", "a"));
+    let _ = format!("{:?}", main);
+    assert!(take().is_empty());
+
+    let main = errors::Main::logged(errors::wrap("b", "a"));
+    assert!(take().is_empty());
+    let printed = format!("{:?}", main);
+    assert!(printed.starts_with("b\n"), "{:?}", printed);
+    assert_eq!(take(), [(Level::Error, "b: a".to_string())]);
+    assert_eq!(main.code(), 1);
+}
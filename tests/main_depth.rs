@@ -0,0 +1,36 @@
+#![cfg(feature = "std")]
+
+extern crate errors;
+
+use std::env;
+
+// The depth is read from the environment, so this lives in its own test
+// binary to not race with other tests.
+#[test]
+fn main_max_depth() {
+    let main = || errors::Main::from(errors::wrap("d", errors::wrap("c", errors::wrap("b", "a"))));
+
+    env::remove_var("ERRORS_MAX_DEPTH");
+    let full = format!("{:?}", main());
+    assert!(full.ends_with("\nCaused by: a"), "{}", full);
+
+    env::set_var("ERRORS_MAX_DEPTH", "1");
+    let capped = format!("{:?}", main());
+    assert!(capped.contains("\nCaused by: c"), "{}", capped);
+    assert!(!capped.contains("\nCaused by: b"), "{}", capped);
+
+    env::set_var("ERRORS_MAX_DEPTH", "0");
+    let top = format!("{:?}", main());
+    assert!(top.starts_with("d\n"), "{}", top);
+    assert!(!top.contains("Caused by"), "{}", top);
+
+    // not a number is the same as unset
+    env::set_var("ERRORS_MAX_DEPTH", "lots");
+    assert_eq!(format!("{:?}", main()).matches("Caused by").count(), 3);
+
+    // past what a precision can hold is no limit
+    env::set_var("ERRORS_MAX_DEPTH", "70000");
+    assert_eq!(format!("{:?}", main()).matches("Caused by").count(), 3);
+
+    env::remove_var("ERRORS_MAX_DEPTH");
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "json")]
+
+extern crate errors;
+
+use std::env;
+
+// The format is read from the environment, so this lives in its own test
+// binary to not race with other tests.
+#[test]
+fn main_json_toggle() {
+    let main = errors::Main::from(errors::wrap("ship exploded", "cat hair in generator"));
+
+    env::remove_var("ERRORS_FORMAT");
+    let human = format!("{:?}", main);
+    assert!(human.starts_with("ship exploded\n    at tests/main_json.rs:"), "{}", human);
+    assert!(human.ends_with("\nCaused by: cat hair in generator"), "{}", human);
+
+    env::set_var("ERRORS_FORMAT", "json");
+    assert_eq!(
+        format!("{:?}", main),
+        r#"{"causes":["cat hair in generator"],"error":"ship exploded"}"#,
+    );
+
+    let main = errors::Main::from("ship exploded");
+    assert_eq!(format!("{:?}", main), r#"{"causes":[],"error":"ship exploded"}"#);
+
+    // the hidden chain of an opaque error is printed, like the human output
+    let main = errors::Main::from(errors::wrap("launch failed", errors::opaque(errors::wrap("b", "a"))));
+    assert_eq!(format!("{:?}", main), r#"{"causes":["b","a"],"error":"launch failed"}"#);
+
+    // and an error printing its sources in `{}` only gives its own message
+    let main = errors::Main::from(errors::into_io(errors::wrap("ship exploded", "cat hair in generator")));
+    assert_eq!(
+        format!("{:?}", main),
+        r#"{"causes":["cat hair in generator"],"error":"ship exploded"}"#,
+    );
+
+    env::set_var("ERRORS_FORMAT", "human");
+    let main = errors::Main::from("ship exploded");
+    assert_eq!(format!("{:?}", main), "ship exploded");
+}
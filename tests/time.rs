@@ -0,0 +1,34 @@
+#![cfg(feature = "time")]
+
+extern crate errors;
+
+use std::time::SystemTime;
+
+#[test]
+fn captures_creation_time() {
+    let before = SystemTime::now();
+    let err = errors::wrap("ship exploded", "cat hair in generator");
+    let after = SystemTime::now();
+
+    let created = errors::created(&err).expect("time captured");
+    assert!(before <= created && created <= after);
+
+    // foreign errors have no creation time
+    let io = std::io::Error::other("boom");
+    assert!(errors::created(&io).is_none());
+}
+
+#[test]
+fn only_alternate_includes_time() {
+    let err = errors::new("ship exploded");
+
+    assert_eq!(format!("{}", err), "ship exploded");
+    assert_eq!(format!("{:+}", err), "ship exploded");
+
+    let alt = format!("{:#}", err);
+    let stamp = alt.lines().nth(2).expect("timestamp line");
+    assert!(stamp.starts_with("    at "), "{}", alt);
+    assert!(stamp.ends_with('Z'), "{}", alt);
+    // "    at " + "YYYY-MM-DDTHH:MM:SSZ"
+    assert_eq!(stamp.len(), 7 + 20, "{}", alt);
+}
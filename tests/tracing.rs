@@ -0,0 +1,114 @@
+#![cfg(feature = "tracing")]
+
+extern crate errors;
+extern crate tracing;
+extern crate tracing_core;
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+// Records the events it sees, with the name of the span they are in, and
+// tracks the current span, so that `Span::current()` works.
+#[derive(Default)]
+struct Recorder {
+    next_id: AtomicU64,
+    spans: Mutex<Vec<&'static Metadata<'static>>>,
+    stack: Mutex<Vec<Id>>,
+    events: Mutex<Vec<Recorded>>,
+}
+
+#[derive(Debug, Default)]
+struct Recorded {
+    span: Option<&'static str>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for Recorded {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.fields.push((field.name(), format!("{:?}", value)));
+    }
+}
+
+impl Recorded {
+    fn field(&self, name: &str) -> &str {
+        &self.fields.iter().find(|&&(n, _)| n == name).expect("field").1
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        self.spans.lock().unwrap().push(span.metadata());
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let mut recorded = Recorded {
+            span: self.current_span().metadata().map(|m| m.name()),
+            ..Recorded::default()
+        };
+        event.record(&mut recorded);
+        self.events.lock().unwrap().push(recorded);
+    }
+
+    fn enter(&self, span: &Id) {
+        self.stack.lock().unwrap().push(span.clone());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.stack.lock().unwrap().pop();
+    }
+
+    fn current_span(&self) -> Current {
+        match self.stack.lock().unwrap().last() {
+            Some(id) => {
+                let meta = self.spans.lock().unwrap()[id.into_u64() as usize - 1];
+                Current::new(id.clone(), meta)
+            }
+            None => Current::none(),
+        }
+    }
+}
+
+#[test]
+fn captures_span_and_emits_chain() {
+    let recorder = std::sync::Arc::new(Recorder::default());
+
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let err = errors::wrap("ship exploded", "cat hair in generator");
+        assert!(errors::span(&err).is_none());
+
+        let span = tracing::error_span!("launch");
+        let err = span.in_scope(|| errors::wrap("ship exploded", "cat hair in generator"));
+        drop(span);
+
+        let captured = errors::span(&err).expect("span captured");
+        assert_eq!(captured.metadata().unwrap().name(), "launch");
+
+        // foreign errors have no span
+        assert!(errors::span(&std::io::Error::other("boom")).is_none());
+
+        errors::emit(&err);
+    });
+
+    let events = recorder.events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.span, Some("launch"));
+    assert_eq!(event.field("message"), "ship exploded: cat hair in generator");
+    assert_eq!(event.field("error"), "ship exploded");
+    assert_eq!(event.field("causes"), r#"["cat hair in generator"]"#);
+}